@@ -2,6 +2,8 @@ use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 
 pub const DEFAULT_MARKETPLACE_SOURCE: &str = "paternosterrack/rack";
+/// Adapter target that selects every registered adapter.
+pub const ALL_ADAPTERS: &str = "all";
 
 #[derive(Parser, Debug)]
 #[command(name = "pater", version, about = "Paternoster Rack CLI")]
@@ -31,8 +33,8 @@ pub enum Commands {
     Plan {
         #[arg(long)]
         intent: String,
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        agent: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        agent: String,
     },
     Show {
         plugin: String,
@@ -41,11 +43,15 @@ pub enum Commands {
         target: String,
         #[arg(long, value_enum, default_value_t = InstallScope::User)]
         scope: InstallScope,
+        /// Only expose the plugin to this adapter (repeatable).
+        #[arg(long = "adapter")]
+        adapters: Vec<String>,
     },
     Apply {
         target: String,
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        target_adapter: AdapterTarget,
+        /// Adapter to pin the plugin to and sync; `all` leaves it unpinned.
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        target_adapter: String,
         #[arg(long, value_enum, default_value_t = InstallScope::User)]
         scope: InstallScope,
     },
@@ -65,10 +71,19 @@ pub enum Commands {
     Remove {
         plugin: String,
     },
+    Gc {
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    #[command(name = "self")]
+    SelfCmd {
+        #[command(subcommand)]
+        command: SelfCommands,
+    },
     List,
     Capabilities {
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        agent: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        agent: String,
     },
     Hook {
         #[command(subcommand)]
@@ -98,8 +113,8 @@ pub enum Commands {
     Ensure {
         #[arg(long)]
         intent: String,
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        agent: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        agent: String,
     },
     Check,
 }
@@ -130,8 +145,26 @@ pub enum TrustCommands {
 pub enum PolicyCommands {
     Eval {
         plugin: String,
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        agent: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        agent: String,
+        /// Run every rule and print each outcome, not just the first denial.
+        #[arg(long, default_value_t = false)]
+        explain: bool,
+        /// Evaluate against this policy file alone instead of the configured layers.
+        #[arg(long)]
+        policy: Option<String>,
+    },
+    /// Check every installed plugin against the current policy; exits 1 on violations.
+    Audit {
+        /// Hide violators from every adapter (files are kept); release ones that now pass.
+        #[arg(long, default_value_t = false)]
+        quarantine: bool,
+    },
+    /// List the policy layers (system, user, project, env) and what each sets.
+    Show {
+        /// Also print the merged policy with the origin of each field.
+        #[arg(long, default_value_t = false)]
+        effective: bool,
     },
 }
 
@@ -245,6 +278,18 @@ pub enum SubagentCommands {
         rack_dir: String,
         #[arg(long, default_value = "Subagent purpose")]
         purpose: String,
+        /// System prompt body.
+        #[arg(long, conflicts_with = "prompt_file")]
+        prompt: Option<String>,
+        /// Read the system prompt body from a file.
+        #[arg(long)]
+        prompt_file: Option<String>,
+        /// Tool the subagent may use (repeatable).
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+        /// Model hint, e.g. `sonnet`.
+        #[arg(long)]
+        model: Option<String>,
     },
     Remove {
         plugin: String,
@@ -302,36 +347,56 @@ pub enum McpCommands {
 
 #[derive(Subcommand, Debug)]
 pub enum AdapterCommands {
+    List,
     Sync {
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        target: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        target: String,
+        /// Print a unified diff of every file sync would touch; write nothing.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
     },
     Smoke {
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        target: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        target: String,
+    },
+    Unsync {
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        target: String,
     },
     Doctor,
+    /// Only expose an installed plugin to the given adapters.
+    Pin {
+        plugin: String,
+        #[arg(required = true)]
+        adapters: Vec<String>,
+    },
+    /// Expose an installed plugin to every adapter it supports again.
+    Unpin {
+        plugin: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SelfCommands {
+    /// Revert agent configs and delete pater's runtime, store, caches and state.
+    Uninstall {
+        /// Also delete ~/.config/pater (policy, trust keys, adapter specs, audit log).
+        #[arg(long, default_value_t = false)]
+        purge: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 pub enum RuntimeCommands {
     Path,
     Status,
+    Verify,
     Sync {
-        #[arg(long, value_enum, default_value_t = AdapterTarget::All)]
-        target: AdapterTarget,
+        #[arg(long, default_value = ALL_ADAPTERS)]
+        target: String,
     },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ValueEnum)]
-#[serde(rename_all = "lowercase")]
-pub enum AdapterTarget {
-    All,
-    Claude,
-    Codex,
-    Openclaw,
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize, ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum InstallScope {
@@ -4,6 +4,7 @@ use crate::cli::{
 };
 use crate::domain::constants::OFFICIAL_RACK_PUBKEY_HEX;
 use crate::domain::models::{JsonOut, PolicyFile, TrustStatus};
+use crate::rack;
 use crate::services::authoring::{
     hook_create, hook_remove, mcp_create, mcp_remove, plugin_create, plugin_remove, plugin_update,
     skill_create, skill_remove, subagent_create, subagent_remove,
@@ -196,8 +197,23 @@ pub fn handle_author_commands(cli: &Cli) -> anyhow::Result<bool> {
                 name,
                 rack_dir,
                 purpose,
+                prompt,
+                prompt_file,
+                tools,
+                model,
             } => {
-                subagent_create(rack_dir, plugin, name, purpose)?;
+                let prompt = match prompt_file {
+                    Some(path) => Some(std::fs::read_to_string(path)?),
+                    None => prompt.clone(),
+                };
+                let subagent = rack::Subagent {
+                    name: name.clone(),
+                    purpose: purpose.clone(),
+                    prompt,
+                    tools: tools.clone(),
+                    model: model.clone(),
+                };
+                subagent_create(rack_dir, plugin, &subagent)?;
                 print_one(cli.json, "created", |_| {
                     format!("subagent {}/{} created", plugin, name)
                 })?;
@@ -1,33 +1,56 @@
 use crate::cli::{
-    AdapterCommands, AdapterTarget, Cli, Commands, HookCommands, InstallScope, PolicyCommands,
-    RemoteCommands, RuntimeCommands, DEFAULT_MARKETPLACE_SOURCE,
+    AdapterCommands, Cli, Commands, HookCommands, InstallScope, PolicyCommands, RemoteCommands,
+    RuntimeCommands, SelfCommands, ALL_ADAPTERS, DEFAULT_MARKETPLACE_SOURCE,
 };
 use crate::domain::models::{
     CapabilitiesReport, DiscoverItem, InstalledPlugin, JsonOut, MarketRef, PlanReport, PolicyFile,
     State, TrustStatus,
 };
 use crate::rack;
-use crate::services::adapters::{adapter_doctor, adapter_smoke, sync_installed};
+use crate::services::adapters::{
+    adapter_doctor, adapter_names, adapter_smoke, check_pin, resolve_adapters, sync_adapters,
+    sync_installed, unsync_adapters,
+};
+use crate::services::gc::{collect_garbage, prune_removed};
 use crate::services::marketplace::{
-    checked_load_marketplace, discover_across, enforce_policy_for_plugin, parse_target,
-    policy_eval_for_plugin, recommend_plugins, run_rack_license_audit, show_plugin, update_plugins,
+    check_agent_compat, checked_load_marketplace, discover_across, enforce_policy_for_plugin,
+    parse_target, policy_audit, policy_eval_for_plugin, recommend_plugins, resolve_installed,
+    run_rack_license_audit, show_plugin, update_plugins,
 };
+use crate::services::migrations::{check_existing, REGISTRY_SCHEMA};
 use crate::services::output::{print_one, print_out};
+use crate::services::policy::{load_policy_file, policy_show};
 use crate::services::release_check::build_release_check_report;
 use crate::services::storage::{
     audit, materialize_plugin, runtime_base_dir, runtime_bridges_dir, runtime_registry_path,
-    save_lockfile, save_state, upsert_installed,
+    save_lockfile, save_state, store_base_dir, upsert_installed,
 };
+use crate::services::store::verify_installed;
 use crate::services::trust::{list_pubkeys, verify_marketplace_signature};
+use crate::services::uninstall::self_uninstall;
 use std::path::Path;
 
+/// Installs `plugin`. An empty `adapters` keeps any pin from an earlier install.
 fn install_entry(
     state: &mut State,
     plugin: &DiscoverItem,
     scope: InstallScope,
+    adapters: &[String],
 ) -> anyhow::Result<InstalledPlugin> {
+    check_pin(&plugin.name, &plugin.agents, adapters)?;
+    let adapters = if adapters.is_empty() {
+        state
+            .installed
+            .iter()
+            .find(|i| i.name == plugin.name && i.marketplace == plugin.marketplace)
+            .map(|i| i.adapters.clone())
+            .unwrap_or_default()
+    } else {
+        adapters.to_vec()
+    };
     let source_path = rack::resolve_plugin_path(&plugin.marketplace_source, &plugin.source)?;
-    let local_path = materialize_plugin(&plugin.name, &source_path)?;
+    let (local_path, store_hash) =
+        materialize_plugin(&plugin.marketplace, &plugin.name, &source_path)?;
     let entry = InstalledPlugin {
         name: plugin.name.clone(),
         marketplace: plugin.marketplace.clone(),
@@ -37,11 +60,42 @@ fn install_entry(
         version: plugin.version.clone(),
         permissions: plugin.permissions.clone(),
         scope,
+        store_hash: Some(store_hash),
+        agents: plugin.agents.clone(),
+        adapters,
+        quarantined: None,
     };
     upsert_installed(state, entry.clone());
     Ok(entry)
 }
 
+/// Pins an installed plugin to `adapters` (empty unpins) and resyncs.
+fn set_adapter_pin(
+    state: &mut State,
+    policy: &PolicyFile,
+    plugin: &str,
+    adapters: &[String],
+) -> anyhow::Result<InstalledPlugin> {
+    let (name, market) = resolve_installed(state, plugin)?
+        .ok_or_else(|| anyhow::anyhow!("plugin not installed: {}", plugin))?;
+    let entry = state
+        .installed
+        .iter_mut()
+        .find(|p| p.name == name && p.marketplace == market)
+        .expect("resolved plugin is installed");
+    check_pin(&entry.name, &entry.agents, adapters)?;
+    entry.adapters = adapters.to_vec();
+    let entry = entry.clone();
+    audit(
+        "adapter_pin",
+        serde_json::json!({"plugin": entry.name, "marketplace": entry.marketplace, "adapters": adapters}),
+    );
+    save_state(state)?;
+    save_lockfile(state)?;
+    sync_installed(state, policy, ALL_ADAPTERS)?;
+    Ok(entry)
+}
+
 pub fn handle_runtime_commands(
     cli: &Cli,
     state: &mut State,
@@ -54,6 +108,7 @@ pub fn handle_runtime_commands(
             RuntimeCommands::Path => {
                 let data = serde_json::json!({
                     "base": runtime_base_dir()?,
+                    "store": store_base_dir()?,
                     "plugins": runtime_base_dir()?.join("plugins"),
                     "registry": runtime_registry_path()?,
                     "bridges": runtime_bridges_dir()?,
@@ -75,8 +130,28 @@ pub fn handle_runtime_commands(
                     )
                 })?;
             }
+            RuntimeCommands::Verify => {
+                let checks = verify_installed(state)?;
+                let all_ok = checks.iter().all(|c| c.status == "ok");
+                if cli.json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&JsonOut {
+                            ok: all_ok,
+                            data: checks
+                        })?
+                    );
+                } else {
+                    for c in checks {
+                        println!("{}\t{}", c.name, c.status);
+                    }
+                }
+                if !all_ok {
+                    std::process::exit(1);
+                }
+            }
             RuntimeCommands::Sync { target } => {
-                sync_installed(state, target.clone())?;
+                sync_installed(state, policy, target)?;
                 let registry_path = runtime_registry_path()?;
                 let report = runtime_status_report(state, &registry_path)?;
                 if cli.json {
@@ -88,10 +163,7 @@ pub fn handle_runtime_commands(
                         })?
                     );
                 } else {
-                    println!(
-                        "runtime sync completed for target={}",
-                        format!("{:?}", target).to_ascii_lowercase()
-                    );
+                    println!("runtime sync completed for target={}", target);
                     println!("registry: {}", registry_path.to_string_lossy());
                 }
             }
@@ -110,11 +182,12 @@ pub fn handle_runtime_commands(
             })?;
         }
         Commands::Plan { intent, agent } => {
+            resolve_adapters(agent)?;
             let items = discover_across(all_markets, Some(intent), policy)?;
             let recs = recommend_plugins(items, Some(intent));
             let report = PlanReport {
                 intent: intent.clone(),
-                agent: format!("{:?}", agent).to_lowercase(),
+                agent: agent.clone(),
                 recommendations: recs,
             };
             if cli.json {
@@ -151,20 +224,27 @@ pub fn handle_runtime_commands(
                 if !p.permissions.is_empty() {
                     println!("permissions: {}", p.permissions.join(", "));
                 }
+                if !p.agents.is_empty() {
+                    println!("agents: {}", p.agents.join(", "));
+                }
             }
         }
-        Commands::Install { target, scope } => {
+        Commands::Install {
+            target,
+            scope,
+            adapters,
+        } => {
             let (name, market) = parse_target(target);
             let p = show_plugin(all_markets, &name, market.as_deref(), policy)?;
             enforce_policy_for_plugin(policy, &p)?;
-            let entry = install_entry(state, &p, scope.clone())?;
+            let entry = install_entry(state, &p, scope.clone(), adapters)?;
             audit(
                 "install",
                 serde_json::json!({"plugin": entry.name, "marketplace": entry.marketplace}),
             );
             save_state(state)?;
             save_lockfile(state)?;
-            sync_installed(state, AdapterTarget::All)?;
+            sync_installed(state, policy, ALL_ADAPTERS)?;
 
             if cli.json {
                 println!(
@@ -176,7 +256,7 @@ pub fn handle_runtime_commands(
                 );
             } else {
                 println!("installed {}@{}", entry.name, entry.marketplace);
-                println!("adapter sync complete (claude/codex/openclaw)");
+                println!("adapter sync complete ({})", adapter_names()?.join("/"));
             }
         }
         Commands::Apply {
@@ -187,11 +267,18 @@ pub fn handle_runtime_commands(
             let (name, market) = parse_target(target);
             let p = show_plugin(all_markets, &name, market.as_deref(), policy)?;
             enforce_policy_for_plugin(policy, &p)?;
-            let entry = install_entry(state, &p, scope.clone())?;
+            check_agent_compat(&p, target_adapter)?;
+            let pin = if target_adapter == ALL_ADAPTERS {
+                Vec::new()
+            } else {
+                vec![target_adapter.clone()]
+            };
+            let entry = install_entry(state, &p, scope.clone(), &pin)?;
             save_state(state)?;
             save_lockfile(state)?;
-            sync_installed(state, target_adapter.clone())?;
-            let smoke = adapter_smoke(state, target_adapter.clone())?;
+            // A pin can drop the plugin from other adapters, so resync them all.
+            sync_installed(state, policy, ALL_ADAPTERS)?;
+            let smoke = adapter_smoke(state, target_adapter)?;
             if cli.json {
                 println!(
                     "{}",
@@ -205,26 +292,60 @@ pub fn handle_runtime_commands(
             }
         }
         Commands::Adapter { command } => match command {
-            AdapterCommands::Sync { target } => {
-                sync_installed(state, target.clone())?;
-                audit(
-                    "adapter_sync",
-                    serde_json::json!({"target": format!("{:?}", target)}),
-                );
+            AdapterCommands::List => {
+                print_out(cli.json, &adapter_names()?, |n| n.clone())?;
+            }
+            AdapterCommands::Sync {
+                target,
+                dry_run: true,
+            } => {
+                let report = sync_adapters(state, policy, target, true)?;
+                if cli.json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&JsonOut {
+                            ok: true,
+                            data: report.changes
+                        })?
+                    );
+                } else if report.changes.is_empty() {
+                    println!("adapter sync: no changes");
+                } else {
+                    for c in report.changes {
+                        print!("{}", c.diff);
+                    }
+                }
+            }
+            AdapterCommands::Sync { target, .. } => {
+                let report = sync_adapters(state, policy, target, false)?;
+                audit("adapter_sync", serde_json::json!({"target": target}));
                 if cli.json {
                     println!(
                         "{}",
                         serde_json::to_string_pretty(&JsonOut {
                             ok: true,
-                            data: "synced"
+                            data: serde_json::json!({
+                                "status": "synced",
+                                "skipped_hooks": report.skipped_hooks,
+                                "skipped_plugins": report.skipped_plugins,
+                            })
                         })?
                     );
                 } else {
                     println!("adapter sync completed");
+                    for h in report.skipped_hooks {
+                        println!(
+                            "skipped hook\t{}\t{}\t{}\t{}",
+                            h.adapter, h.plugin, h.event, h.reason
+                        );
+                    }
+                    for p in report.skipped_plugins {
+                        println!("skipped plugin\t{}\t{}\t{}", p.adapter, p.plugin, p.reason);
+                    }
                 }
             }
             AdapterCommands::Smoke { target } => {
-                let report = adapter_smoke(state, target.clone())?;
+                let report = adapter_smoke(state, target)?;
                 if cli.json {
                     println!(
                         "{}",
@@ -236,9 +357,32 @@ pub fn handle_runtime_commands(
                 } else {
                     for r in report {
                         println!("{}\t{}", r.adapter, r.status);
+                        for c in r.checks {
+                            match c.detail {
+                                Some(d) => println!("  {}\t{}\t{}", c.name, c.status, d),
+                                None => println!("  {}\t{}", c.name, c.status),
+                            }
+                        }
                     }
                 }
             }
+            AdapterCommands::Unsync { target } => {
+                let report = unsync_adapters(target)?;
+                audit("adapter_unsync", serde_json::json!({"target": target}));
+                print_out(cli.json, &report, |r| {
+                    format!("{}\tconfig {}", r.adapter, r.config)
+                })?;
+            }
+            AdapterCommands::Pin { plugin, adapters } => {
+                let entry = set_adapter_pin(state, policy, plugin, adapters)?;
+                print_one(cli.json, entry, |e| {
+                    format!("{} pinned to {}", e.name, e.adapters.join(", "))
+                })?;
+            }
+            AdapterCommands::Unpin { plugin } => {
+                let entry = set_adapter_pin(state, policy, plugin, &[])?;
+                print_one(cli.json, entry, |e| format!("{} unpinned", e.name))?;
+            }
             AdapterCommands::Doctor => {
                 let report = adapter_doctor(state)?;
                 audit(
@@ -285,17 +429,26 @@ pub fn handle_runtime_commands(
             );
             save_state(state)?;
             save_lockfile(state)?;
-            sync_installed(state, AdapterTarget::All)?;
+            sync_installed(state, policy, ALL_ADAPTERS)?;
             print_out(cli.json, &report, |r| format!("{}\t{}", r.name, r.status))?;
         }
         Commands::Remove { plugin } => {
-            let before = state.installed.len();
-            state.installed.retain(|p| p.name != *plugin);
+            let mut gone = Vec::new();
+            if let Some((name, market)) = resolve_installed(state, plugin)? {
+                let (drop, keep) = std::mem::take(&mut state.installed)
+                    .into_iter()
+                    .partition(|p| p.name == name && p.marketplace == market);
+                state.installed = keep;
+                gone = drop;
+            }
             audit("remove", serde_json::json!({"plugin": plugin}));
             save_state(state)?;
             save_lockfile(state)?;
-            sync_installed(state, AdapterTarget::All)?;
-            let removed = before.saturating_sub(state.installed.len());
+            sync_installed(state, policy, ALL_ADAPTERS)?;
+            for p in &gone {
+                prune_removed(state, p)?;
+            }
+            let removed = gone.len();
             if cli.json {
                 println!(
                     "{}",
@@ -308,13 +461,76 @@ pub fn handle_runtime_commands(
                 println!("removed {} entries", removed);
             }
         }
+        Commands::SelfCmd { command } => match command {
+            SelfCommands::Uninstall { purge } => {
+                let report = self_uninstall(*purge)?;
+                if !purge {
+                    audit(
+                        "self_uninstall",
+                        serde_json::json!({"removed": report.removed.len()}),
+                    );
+                }
+                print_one(cli.json, report, |r| {
+                    let mut lines = Vec::new();
+                    for u in &r.unsynced {
+                        lines.push(format!("unsynced {}\tconfig {}", u.adapter, u.config));
+                    }
+                    for path in &r.removed {
+                        lines.push(format!("removed {}", path));
+                    }
+                    for path in &r.kept {
+                        lines.push(format!("kept {}", path));
+                    }
+                    lines.push(
+                        "remove the pater binary itself with `cargo uninstall pater`".to_string(),
+                    );
+                    lines.join("\n")
+                })?;
+            }
+        },
+        Commands::Gc { dry_run } => {
+            let report = collect_garbage(state, *dry_run)?;
+            if !dry_run {
+                audit(
+                    "gc",
+                    serde_json::json!({"items": report.items.len(), "bytes": report.reclaimed_bytes}),
+                );
+            }
+            if cli.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&JsonOut {
+                        ok: true,
+                        data: report
+                    })?
+                );
+            } else {
+                let verb = if report.dry_run {
+                    "would remove"
+                } else {
+                    "removed"
+                };
+                for item in &report.items {
+                    println!(
+                        "{} {}\t{}\t{} bytes",
+                        verb, item.kind, item.path, item.bytes
+                    );
+                }
+                println!(
+                    "gc: {} {} entries ({} bytes)",
+                    verb,
+                    report.items.len(),
+                    report.reclaimed_bytes
+                );
+            }
+        }
         Commands::List => {
             print_out(cli.json, &state.installed, |p| {
                 format!("{}\t{}\t{:?}", p.name, p.marketplace, p.scope)
             })?;
         }
         Commands::Capabilities { agent } => {
-            let smoke = adapter_smoke(state, agent.clone())?;
+            let smoke = adapter_smoke(state, agent)?;
             let report = CapabilitiesReport {
                 installed_count: state.installed.len(),
                 installed_plugins: state.installed.iter().map(|p| p.name.clone()).collect(),
@@ -423,11 +639,11 @@ pub fn handle_runtime_commands(
             let (name, market) = parse_target(&target);
             let p = show_plugin(all_markets, &name, market.as_deref(), policy)?;
             enforce_policy_for_plugin(policy, &p)?;
-            let entry = install_entry(state, &p, InstallScope::User)?;
+            let entry = install_entry(state, &p, InstallScope::User, &[])?;
             save_state(state)?;
             save_lockfile(state)?;
-            sync_installed(state, agent.clone())?;
-            let smoke = adapter_smoke(state, agent.clone())?;
+            sync_installed(state, policy, agent)?;
+            let smoke = adapter_smoke(state, agent)?;
             if cli.json {
                 println!(
                     "{}",
@@ -453,16 +669,43 @@ pub fn handle_runtime_commands(
             };
             let doctor = adapter_doctor(state)?;
             let rack_license_audit = run_rack_license_audit(&cli.marketplace);
-            let report = build_release_check_report(trust, doctor, rack_license_audit);
+            let report = build_release_check_report(
+                trust,
+                doctor,
+                rack_license_audit,
+                policy.expired_exceptions.clone(),
+            );
             print_one(cli.json, report, |r| {
-                format!("release-check: {}", r.overall)
+                let mut lines = vec![format!("release-check: {}", r.overall)];
+                for e in &r.expired_policy_exceptions {
+                    lines.push(format!(
+                        "expired policy exception: {} in {} ({}), expired {}",
+                        e.plugin, e.field, e.layer, e.expires
+                    ));
+                }
+                lines.join("\n")
             })?;
         }
         Commands::Policy { command } => match command {
-            PolicyCommands::Eval { plugin, agent } => {
+            PolicyCommands::Eval {
+                plugin,
+                agent,
+                explain,
+                policy: candidate,
+            } => {
+                resolve_adapters(agent)?;
                 let (name, market) = parse_target(plugin);
                 let p = show_plugin(all_markets, &name, market.as_deref(), policy)?;
-                let eval = policy_eval_for_plugin(policy, &p, agent.clone());
+                let candidate = candidate
+                    .as_deref()
+                    .map(|path| load_policy_file(Path::new(path)))
+                    .transpose()?;
+                let eval = policy_eval_for_plugin(
+                    candidate.as_ref().unwrap_or(policy),
+                    &p,
+                    agent,
+                    *explain,
+                );
                 if cli.json {
                     println!(
                         "{}",
@@ -478,7 +721,81 @@ pub fn handle_runtime_commands(
                         if eval.allowed { "allowed" } else { "denied" }
                     );
                     println!("reason: {}", eval.reason);
+                    if !eval.blocked_permissions.is_empty() {
+                        println!(
+                            "blocked permissions: {}",
+                            eval.blocked_permissions.join(", ")
+                        );
+                    }
+                    for t in eval.trace.iter().flatten() {
+                        println!("  {}\t{}\t{}", t.rule, t.outcome, t.detail);
+                    }
+                }
+            }
+            PolicyCommands::Audit { quarantine } => {
+                let report = policy_audit(state, policy, *quarantine);
+                audit(
+                    "policy-audit",
+                    serde_json::json!({"violations": report.violations, "quarantine": quarantine}),
+                );
+                if *quarantine {
+                    save_state(state)?;
+                    save_lockfile(state)?;
+                    sync_installed(state, policy, ALL_ADAPTERS)?;
                 }
+                let clean = report.violations == 0;
+                if cli.json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&JsonOut {
+                            ok: clean,
+                            data: report
+                        })?
+                    );
+                } else {
+                    for p in &report.plugins {
+                        let (status, reason) = match (p.allowed, p.quarantined) {
+                            (true, false) => ("ok", p.reason.as_str()),
+                            // Passes now, but only `--quarantine` releases it.
+                            (true, true) => (
+                                "quarantined",
+                                "allowed again; rerun with --quarantine to release",
+                            ),
+                            (false, true) => ("quarantined", p.reason.as_str()),
+                            (false, false) => ("violation", p.reason.as_str()),
+                        };
+                        println!("{}@{}\t{}\t{}", p.plugin, p.marketplace, status, reason);
+                    }
+                    println!(
+                        "policy audit: checked={} violations={}",
+                        report.checked, report.violations
+                    );
+                }
+                if !clean {
+                    std::process::exit(1);
+                }
+            }
+            PolicyCommands::Show { effective } => {
+                let report = policy_show(*effective)?;
+                print_one(cli.json, report, |r| {
+                    let mut lines = r
+                        .layers
+                        .iter()
+                        .map(|l| {
+                            let status = if l.loaded { "loaded" } else { "absent" };
+                            format!("{}\t{}\t{}", l.name, l.path, status)
+                        })
+                        .collect::<Vec<_>>();
+                    for f in r.effective.iter().flatten() {
+                        let origin = if f.origin.is_empty() {
+                            "default".to_string()
+                        } else {
+                            f.origin.join(", ")
+                        };
+                        lines.push(format!("{} = {}\t({})", f.field, f.value, origin));
+                    }
+                    lines.join("\n")
+                })?;
             }
         },
         Commands::Trust { .. } | Commands::Rack { .. } | Commands::Author { .. } => {
@@ -498,6 +815,7 @@ fn runtime_status_report(state: &State, registry_path: &Path) -> anyhow::Result<
     let registry_exists = registry_path.exists();
 
     if registry_exists {
+        check_existing(registry_path, &REGISTRY_SCHEMA)?;
         let raw = std::fs::read_to_string(registry_path)?;
         if let Ok(v) = serde_json::from_str::<serde_json::Value>(&raw) {
             plugins_count = v
@@ -1,2 +1,8 @@
 pub const OFFICIAL_RACK_PUBKEY_HEX: &str =
     "5aefcc2a6716ef9fab24dc3865013e29a8d579e4dda33bf753a7cd7a8d14450a";
+
+/// Current on-disk schema versions. Bump together with a migration in
+/// `services/migrations.rs`.
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+pub const LOCKFILE_SCHEMA_VERSION: u32 = 1;
+pub const REGISTRY_SCHEMA_VERSION: u32 = 1;
@@ -7,7 +7,7 @@
 //!
 //! ## Files
 //! - `models.rs` — state, policy, report/output structs.
-//! - `constants.rs` — stable constants (e.g. official trust key).
+//! - `constants.rs` — stable constants (e.g. official trust key, schema versions).
 //!
 //! ## Rule of thumb
 //! Domain types should be data-only: no filesystem/network side effects.
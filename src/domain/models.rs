@@ -1,5 +1,7 @@
 use crate::cli::InstallScope;
+use crate::domain::constants::STATE_SCHEMA_VERSION;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 fn default_scope() -> InstallScope {
     InstallScope::User
@@ -11,12 +13,24 @@ pub struct JsonOut<T: Serialize> {
     pub data: T,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct State {
+    #[serde(default)]
+    pub schema_version: u32,
     pub marketplaces: Vec<MarketRef>,
     pub installed: Vec<InstalledPlugin>,
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            schema_version: STATE_SCHEMA_VERSION,
+            marketplaces: Vec::new(),
+            installed: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MarketRef {
     pub name: String,
@@ -39,6 +53,28 @@ pub struct InstalledPlugin {
     pub permissions: Vec<String>,
     #[serde(default = "default_scope")]
     pub scope: InstallScope,
+    /// Content hash of the plugin tree in the local store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_hash: Option<String>,
+    /// Agents the marketplace lists as supported; empty means any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<String>,
+    /// Adapters the user pinned the plugin to; empty means every supported one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adapters: Vec<String>,
+    /// Policy violation that got the plugin quarantined by `policy audit
+    /// --quarantine`. Quarantined plugins stay on disk but no adapter sees them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantined: Option<String>,
+}
+
+impl InstalledPlugin {
+    /// Whether sync exposes this plugin to `adapter`.
+    pub fn targets_adapter(&self, adapter: &str) -> bool {
+        self.quarantined.is_none()
+            && (self.agents.is_empty() || self.agents.iter().any(|a| a == adapter))
+            && (self.adapters.is_empty() || self.adapters.iter().any(|a| a == adapter))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -47,32 +83,176 @@ pub struct Lockfile {
     pub plugins: Vec<InstalledPlugin>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Effective policy: every layer merged (see `services::policy`).
+#[derive(Debug, Default)]
 pub struct PolicyFile {
-    #[serde(default)]
     pub general: PolicyGeneral,
+    /// Per-agent policy for agents with an `[agent.<name>]` section in any
+    /// layer: those sections merged over `general`.
+    pub agents: BTreeMap<String, PolicyGeneral>,
+    /// Exceptions dropped because they expired, for `pater check`.
+    pub expired_exceptions: Vec<ExpiredException>,
 }
 
-#[derive(Debug, Deserialize, Default)]
+impl PolicyFile {
+    /// The policy `agent` is held to; `general` for `all` and for agents
+    /// without a section.
+    pub fn for_agent(&self, agent: &str) -> &PolicyGeneral {
+        self.agents.get(agent).unwrap_or(&self.general)
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct PolicyGeneral {
-    #[serde(default)]
     pub require_signed_marketplace: bool,
-    #[serde(default)]
-    pub allowed_sources: Vec<String>,
-    #[serde(default)]
+    /// `None` allows any source.
+    pub allowed_sources: Option<Vec<String>>,
     pub denied_plugins: Vec<String>,
-    #[serde(default)]
     pub blocked_permissions: Vec<String>,
-    #[serde(default)]
+    pub permission_mode: PermissionMode,
+    /// Only consulted in allowlist mode; `None` allows nothing.
+    pub allowed_permissions: Option<Vec<String>>,
     pub block_unknown_licenses: bool,
-    #[serde(default)]
     pub allow_unknown_license_plugins: Vec<String>,
-    #[serde(default)]
     pub allow_external_reference_installs: bool,
-    #[serde(default)]
     pub allow_external_reference_plugins: Vec<String>,
 }
 
+/// How plugin permissions are judged. `denylist` blocks only what
+/// `blocked_permissions` names; `allowlist` also blocks anything
+/// `allowed_permissions` does not name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionMode {
+    #[default]
+    Denylist,
+    Allowlist,
+}
+
+/// One policy file as written. Keys it leaves unset are decided by the
+/// other layers.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct PolicyLayer {
+    #[serde(default)]
+    pub general: PolicyLayerGeneral,
+    /// `[agent.<name>]` sections: the same keys, applied on top of
+    /// `[general]` for that adapter only.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub agent: BTreeMap<String, PolicyLayerGeneral>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct PolicyLayerGeneral {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_signed_marketplace: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_sources: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub denied_plugins: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_permissions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<PermissionMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_permissions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_unknown_licenses: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_unknown_license_plugins: Option<Vec<PolicyException>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_external_reference_installs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_external_reference_plugins: Option<Vec<PolicyException>>,
+}
+
+/// An override list entry: a bare `name[@marketplace]` pattern, or a table
+/// recording who granted it, why, and until when.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum PolicyException {
+    Plain(String),
+    Detailed {
+        plugin: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        approved_by: Option<String>,
+        /// Last day the exception applies, `YYYY-MM-DD` (TOML date or string).
+        #[serde(
+            default,
+            deserialize_with = "date_string",
+            skip_serializing_if = "Option::is_none"
+        )]
+        expires: Option<String>,
+    },
+}
+
+impl PolicyException {
+    pub fn plugin(&self) -> &str {
+        match self {
+            PolicyException::Plain(plugin) | PolicyException::Detailed { plugin, .. } => plugin,
+        }
+    }
+
+    pub fn expires(&self) -> Option<&str> {
+        match self {
+            PolicyException::Plain(_) => None,
+            PolicyException::Detailed { expires, .. } => expires.as_deref(),
+        }
+    }
+}
+
+/// Accepts `expires = 2026-12-31` as well as `expires = "2026-12-31"`.
+fn date_string<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        Text(String),
+        Toml(toml::value::Datetime),
+    }
+    Ok(Option::<Date>::deserialize(d)?.map(|date| match date {
+        Date::Text(s) => s,
+        Date::Toml(dt) => dt.to_string(),
+    }))
+}
+
+/// An exception past its `expires` date; it no longer applies.
+#[derive(Debug, Serialize, Clone)]
+pub struct ExpiredException {
+    /// Layer that grants it, e.g. `user` or `system:agent.codex`.
+    pub layer: String,
+    pub field: String,
+    pub plugin: String,
+    pub expires: String,
+    pub reason: Option<String>,
+    pub approved_by: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PolicyLayerInfo {
+    /// `system`, `user`, `project` or `env`, lowest first.
+    pub name: String,
+    pub path: String,
+    pub loaded: bool,
+    /// Keys the file sets; `null` when it does not exist.
+    pub settings: serde_json::Value,
+}
+
+#[derive(Serialize)]
+pub struct EffectivePolicyField {
+    pub field: String,
+    pub value: serde_json::Value,
+    /// Layers that decided the value; empty means the built-in default.
+    pub origin: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct PolicyShowReport {
+    pub layers: Vec<PolicyLayerInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective: Option<Vec<EffectivePolicyField>>,
+}
+
 #[derive(Serialize, Clone)]
 pub struct DiscoverItem {
     pub marketplace: String,
@@ -84,6 +264,8 @@ pub struct DiscoverItem {
     pub distribution: Option<String>,
     pub license_status: Option<String>,
     pub permissions: Vec<String>,
+    /// Supported agents from marketplace metadata; empty means any.
+    pub agents: Vec<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -103,15 +285,93 @@ pub struct UpdateReport {
     pub status: String,
     pub old_version: Option<String>,
     pub new_version: Option<String>,
+    /// New permissions of a kind the installed version did not have.
     pub added_permissions: Vec<String>,
+    /// New permissions widening one the installed version had, e.g.
+    /// `net:api.github.com` becoming `net:*`.
+    pub broadened_permissions: Vec<String>,
 }
 
 #[derive(Serialize)]
 pub struct SmokeReport {
     pub adapter: String,
+    /// `ok`, or the status of the first failing check.
     pub status: String,
     pub checked_plugins: usize,
     pub missing_plugins: Vec<String>,
+    pub checks: Vec<SmokeCheck>,
+}
+
+#[derive(Serialize)]
+pub struct SmokeCheck {
+    pub name: String,
+    pub status: String,
+    /// What was missing or mismatched, for failing checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct GcItem {
+    pub kind: String,
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub items: Vec<GcItem>,
+    pub reclaimed_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct FileChange {
+    pub path: String,
+    /// `create`, `modify` or `delete`.
+    pub action: String,
+    /// Unified diff from the current content to the new content.
+    pub diff: String,
+    /// Where the prior file was (or, in dry-run, would be) copied.
+    pub backup: Option<String>,
+}
+
+/// A plugin hook an adapter has no native way to run.
+#[derive(Serialize)]
+pub struct SkippedHook {
+    pub adapter: String,
+    pub plugin: String,
+    pub event: String,
+    pub reason: String,
+}
+
+/// An installed plugin an adapter's `[agent.<name>]` policy kept out of sync.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedPlugin {
+    pub adapter: String,
+    pub plugin: String,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct SyncReport {
+    pub changes: Vec<FileChange>,
+    pub skipped_hooks: Vec<SkippedHook>,
+    pub skipped_plugins: Vec<SkippedPlugin>,
+}
+
+#[derive(Serialize)]
+pub struct UnsyncItem {
+    pub adapter: String,
+    /// `restored` (pre-pater bytes put back) or `unpatched` (pater entries removed).
+    pub config: String,
+}
+
+#[derive(Serialize)]
+pub struct UninstallReport {
+    pub unsynced: Vec<UnsyncItem>,
+    pub removed: Vec<String>,
+    pub kept: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -142,6 +402,39 @@ pub struct PolicyEvalReport {
     pub agent: String,
     pub allowed: bool,
     pub reason: String,
+    /// Supported agents from marketplace metadata; empty means any.
+    pub supported_agents: Vec<String>,
+    /// Plugin permissions the policy blocks, whether by
+    /// `blocked_permissions` or by allowlist mode.
+    pub blocked_permissions: Vec<String>,
+    /// Every rule's outcome, with `--explain`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<PolicyRuleTrace>>,
+}
+
+#[derive(Serialize)]
+pub struct PolicyAuditEntry {
+    pub plugin: String,
+    pub marketplace: String,
+    pub allowed: bool,
+    pub reason: String,
+    /// Whether the plugin is quarantined after this audit.
+    pub quarantined: bool,
+}
+
+#[derive(Serialize)]
+pub struct PolicyAuditReport {
+    pub checked: usize,
+    pub violations: usize,
+    pub plugins: Vec<PolicyAuditEntry>,
+}
+
+#[derive(Serialize)]
+pub struct PolicyRuleTrace {
+    pub rule: String,
+    /// `pass`, `deny`, `skip` (nothing to check) or `error`.
+    pub outcome: String,
+    pub detail: String,
 }
 
 #[derive(Serialize)]
@@ -165,6 +458,7 @@ pub struct ReleaseCheckReport {
     pub trust: TrustStatus,
     pub doctor: DoctorReport,
     pub rack_license_audit: String,
+    pub expired_policy_exceptions: Vec<ExpiredException>,
     pub recommendations: Vec<String>,
 }
 
@@ -8,14 +8,13 @@ mod domain;
 mod rack;
 mod services;
 
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, PolicyCommands};
 use commands::{
     handle_author_commands, handle_rack_commands, handle_runtime_commands, handle_trust_commands,
 };
 use domain::models::{MarketRef, State};
-use services::marketplace::{
-    checked_load_marketplace, dedupe_markets, ensure_default_marketplace, load_policy,
-};
+use services::marketplace::{checked_load_marketplace, dedupe_markets, ensure_default_marketplace};
+use services::policy::load_policy;
 use services::storage::load_state;
 
 fn main() {
@@ -102,7 +101,9 @@ fn command_requires_default_marketplace(command: &Commands) -> bool {
             | Commands::Hook { .. }
             | Commands::Validate
             | Commands::Ensure { .. }
-            | Commands::Policy { .. }
+            | Commands::Policy {
+                command: PolicyCommands::Eval { .. }
+            }
     )
 }
 
@@ -144,9 +145,19 @@ fn error_hint(msg: &str) -> &'static str {
     if m.contains("signature") {
         "run `pater trust init` and verify marketplace.sig"
     } else if m.contains("policy") {
-        "review ~/.config/pater/policy.toml"
+        "review the policy layers with `pater policy show --effective`"
     } else if m.contains("not found") {
         "check plugin/marketplace name and run `pater search`"
+    } else if m.contains("unparseable config") {
+        "fix the file or move it aside, then re-run; pater never overwrites configs it cannot parse"
+    } else if m.contains("does not support agent") {
+        "check the plugin's supported agents with `pater show`"
+    } else if m.contains("not installed") {
+        "run `pater list` to see installed plugins"
+    } else if m.contains("unknown adapter") {
+        "run `pater adapter list` to see available adapters"
+    } else if m.contains("newer than supported") {
+        "upgrade pater; backups of migrated files are kept as *.v<N>.bak"
     } else {
         "run `pater --json check` for diagnostics"
     }
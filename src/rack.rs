@@ -1,3 +1,4 @@
+use crate::services::permissions::Permission;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
@@ -34,6 +35,9 @@ pub struct Plugin {
     pub hooks: Vec<Hook>,
     #[serde(default)]
     pub subagents: Vec<Subagent>,
+    /// Agents the plugin works with (adapter names); empty means any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -49,6 +53,15 @@ pub struct Hook {
 pub struct Subagent {
     pub name: String,
     pub purpose: String,
+    /// System prompt body; `purpose` is used when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Tools the subagent may use; empty means the agent's default set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Model hint, e.g. `sonnet`; agents without one ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -57,6 +70,8 @@ pub enum RackError {
     PluginNotFound(String),
     #[error("duplicate plugin name: {0}")]
     DuplicatePlugin(String),
+    #[error("plugin {0}: {1}")]
+    InvalidPermission(String, String),
 }
 
 fn looks_like_github_shorthand(source: &str) -> bool {
@@ -104,16 +119,22 @@ pub fn resolve_marketplace_file(source: &str) -> PathBuf {
     }
 }
 
-fn cache_path(source: &str) -> anyhow::Result<PathBuf> {
-    let home = std::env::var("HOME")?;
+fn cache_id(source: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(source.as_bytes());
-    let id = hex::encode(hasher.finalize());
-    Ok(PathBuf::from(home)
+    hex::encode(hasher.finalize())
+}
+
+pub fn cache_root() -> anyhow::Result<PathBuf> {
+    Ok(crate::services::storage::home_dir()?
         .join(".cache")
-        .join("pater")
+        .join("pater"))
+}
+
+pub fn cache_path(source: &str) -> anyhow::Result<PathBuf> {
+    Ok(cache_root()?
         .join("marketplaces")
-        .join(format!("{}.json", id)))
+        .join(format!("{}.json", cache_id(source))))
 }
 
 fn fetch_marketplace_text(source: &str, timeout_ms: u64) -> anyhow::Result<String> {
@@ -229,26 +250,25 @@ pub fn list_hooks(m: &Marketplace, agent: Option<&str>) -> Vec<Hook> {
     out
 }
 
+/// Authoring checks for `pater validate`. Loading never runs them: a bad
+/// permission only fails its own plugin, through policy.
 pub fn validate(m: &Marketplace) -> anyhow::Result<()> {
     let mut seen = HashSet::new();
     for p in &m.plugins {
         if !seen.insert(&p.name) {
             return Err(RackError::DuplicatePlugin(p.name.clone()).into());
         }
+        for perm in &p.permissions {
+            if let Err(e) = Permission::parse(perm) {
+                return Err(RackError::InvalidPermission(p.name.clone(), e.to_string()).into());
+            }
+        }
     }
     Ok(())
 }
 
-fn repo_cache_path(source: &str) -> anyhow::Result<PathBuf> {
-    let home = std::env::var("HOME")?;
-    let mut hasher = Sha256::new();
-    hasher.update(source.as_bytes());
-    let id = hex::encode(hasher.finalize());
-    Ok(PathBuf::from(home)
-        .join(".cache")
-        .join("pater")
-        .join("repos")
-        .join(id))
+pub fn repo_cache_path(source: &str) -> anyhow::Result<PathBuf> {
+    Ok(cache_root()?.join("repos").join(cache_id(source)))
 }
 
 fn ensure_repo(source: &str) -> anyhow::Result<PathBuf> {
@@ -285,6 +305,27 @@ fn ensure_repo(source: &str) -> anyhow::Result<PathBuf> {
     Ok(cache)
 }
 
+fn is_remote_plugin_source(plugin_source: &str) -> bool {
+    plugin_source.starts_with("http://")
+        || plugin_source.starts_with("https://")
+        || plugin_source.starts_with("git@")
+        || looks_like_github_shorthand(plugin_source)
+}
+
+/// Source of the repo clone `resolve_plugin_path` would use, if any.
+pub fn plugin_repo_source(market_source: &str, plugin_source: &str) -> Option<String> {
+    if plugin_source.starts_with("./") {
+        if !Path::new(market_source).exists() && is_remote(market_source) {
+            return Some(market_source.to_string());
+        }
+        return None;
+    }
+    if is_remote_plugin_source(plugin_source) {
+        return Some(plugin_source.to_string());
+    }
+    None
+}
+
 pub fn resolve_plugin_path(market_source: &str, plugin_source: &str) -> anyhow::Result<PathBuf> {
     if plugin_source.starts_with("./") {
         let mpath = Path::new(market_source);
@@ -303,11 +344,7 @@ pub fn resolve_plugin_path(market_source: &str, plugin_source: &str) -> anyhow::
         }
     }
 
-    if plugin_source.starts_with("http://")
-        || plugin_source.starts_with("https://")
-        || plugin_source.starts_with("git@")
-        || looks_like_github_shorthand(plugin_source)
-    {
+    if is_remote_plugin_source(plugin_source) {
         return ensure_repo(plugin_source);
     }
 
@@ -0,0 +1,204 @@
+//! Subagent definition files written into agent dirs (`~/.claude/agents`,
+//! `~/.codex/prompts`). Every generated file carries `MANAGED_MARKER` in its
+//! frontmatter; files with the marker that the current sync did not produce
+//! are stale and removed.
+
+use super::changes::ChangeSet;
+use super::PluginSubagent;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// YAML comment inside the frontmatter, so it never reaches the model.
+const MANAGED_MARKER: &str = "# managed by pater; regenerated on adapter sync";
+
+/// Agent-facing name for a plugin entry: `<plugin>-<name>`, lowercase with
+/// hyphens only. Also names linked skill dirs.
+pub fn native_name(plugin: &str, name: &str) -> String {
+    let raw = format!("{}-{}", plugin, name).to_ascii_lowercase();
+    let mut out = String::new();
+    for c in raw.chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '-' };
+        if !(c == '-' && out.ends_with('-')) {
+            out.push(c);
+        }
+    }
+    out.trim_matches('-').to_string()
+}
+
+pub fn native_agent_name(subagent: &PluginSubagent) -> String {
+    native_name(&subagent.plugin, &subagent.name)
+}
+
+/// Fails when two `(plugin, name)` entries get the same native name, e.g.
+/// `a-b`/`c` and `a`/`b-c`; otherwise the later one would silently replace
+/// the earlier.
+pub fn check_native_names<'a>(
+    entries: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> anyhow::Result<()> {
+    let mut seen: HashMap<String, (&str, &str)> = HashMap::new();
+    for (plugin, name) in entries {
+        let native = native_name(plugin, name);
+        if let Some((first_plugin, first_name)) = seen.get(&native) {
+            anyhow::bail!(
+                "{} from plugin {} and {} from plugin {} both map to native name {}; rename one",
+                first_name,
+                first_plugin,
+                name,
+                plugin,
+                native
+            );
+        }
+        seen.insert(native, (plugin, name));
+    }
+    Ok(())
+}
+
+/// Native name and rendered file for every subagent.
+pub fn native_agent_files(
+    subagents: &[PluginSubagent],
+    render: fn(&PluginSubagent) -> anyhow::Result<String>,
+) -> anyhow::Result<Vec<(String, String)>> {
+    check_native_names(
+        subagents
+            .iter()
+            .map(|s| (s.plugin.as_str(), s.name.as_str())),
+    )?;
+    subagents
+        .iter()
+        .map(|s| Ok((native_agent_name(s), render(s)?)))
+        .collect()
+}
+
+fn render(frontmatter: serde_yaml::Mapping, body: &str) -> anyhow::Result<String> {
+    let yaml = serde_yaml::to_string(&frontmatter)?;
+    Ok(format!(
+        "---\n{}{}\n---\n\n{}\n",
+        yaml,
+        MANAGED_MARKER,
+        body.trim_end()
+    ))
+}
+
+fn body(subagent: &PluginSubagent) -> &str {
+    subagent.prompt.as_deref().unwrap_or(&subagent.purpose)
+}
+
+/// Claude Code agent definition (`~/.claude/agents/<name>.md`).
+pub fn render_claude_agent(subagent: &PluginSubagent) -> anyhow::Result<String> {
+    let mut fm = serde_yaml::Mapping::new();
+    fm.insert("name".into(), native_agent_name(subagent).into());
+    fm.insert("description".into(), subagent.purpose.clone().into());
+    if !subagent.tools.is_empty() {
+        fm.insert("tools".into(), subagent.tools.join(", ").into());
+    }
+    if let Some(model) = &subagent.model {
+        fm.insert("model".into(), model.clone().into());
+    }
+    render(fm, body(subagent))
+}
+
+/// Codex custom prompt (`~/.codex/prompts/<name>.md`), run as `/<name>`.
+/// Codex prompts have no tool or model settings, so those hints are dropped.
+pub fn render_codex_prompt(subagent: &PluginSubagent) -> anyhow::Result<String> {
+    let mut fm = serde_yaml::Mapping::new();
+    fm.insert("description".into(), subagent.purpose.clone().into());
+    render(fm, body(subagent))
+}
+
+fn is_managed(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|raw| raw.lines().any(|l| l == MANAGED_MARKER))
+}
+
+fn managed_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut out = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "md") && is_managed(p))
+        .collect::<Vec<_>>();
+    out.sort();
+    Ok(out)
+}
+
+/// Writes `files` (name, content) into `dir` and removes stale managed files.
+/// Refuses to overwrite a same-named file pater did not generate.
+pub fn sync_managed_files(
+    dir: &Path,
+    files: &[(String, String)],
+    changes: &mut ChangeSet,
+) -> anyhow::Result<()> {
+    let wanted = files
+        .iter()
+        .map(|(name, _)| dir.join(format!("{}.md", name)))
+        .collect::<Vec<_>>();
+    if let Some(taken) = wanted.iter().find(|p| p.exists() && !is_managed(p)) {
+        anyhow::bail!(
+            "refusing to overwrite {}: file exists and is not managed by pater",
+            taken.display()
+        );
+    }
+    for (path, (_, content)) in wanted.iter().zip(files) {
+        changes.write(path, content)?;
+    }
+    for stale in managed_files(dir)? {
+        if !wanted.contains(&stale) {
+            changes.remove(&stale)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes every managed file from `dir`, then `dir` itself if that left it
+/// empty.
+pub fn remove_managed_files(dir: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+    for path in managed_files(dir)? {
+        changes.remove(&path)?;
+    }
+    if !changes.dry_run {
+        // Only succeeds when nothing else lives there.
+        let _ = std::fs::remove_dir(dir);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subagent() -> PluginSubagent {
+        PluginSubagent {
+            plugin: "commit-commands".to_string(),
+            name: "Commit Helper".to_string(),
+            purpose: "Reviews commits: style & scope".to_string(),
+            prompt: Some("You review commits.\n".to_string()),
+            tools: vec!["Read".to_string(), "Bash".to_string()],
+            model: Some("sonnet".to_string()),
+        }
+    }
+
+    #[test]
+    fn claude_agent_has_frontmatter_marker_and_prompt_body() {
+        let out = render_claude_agent(&subagent()).expect("render");
+        assert!(out.starts_with("---\nname: commit-commands-commit-helper\n"));
+        assert!(out.contains("description: 'Reviews commits: style & scope'\n"));
+        assert!(out.contains("tools: Read, Bash\nmodel: sonnet\n"));
+        assert!(out.contains(&format!("{}\n---\n\nYou review commits.\n", MANAGED_MARKER)));
+
+        let codex = render_codex_prompt(&subagent()).expect("render");
+        assert!(!codex.contains("tools:"));
+        assert!(codex.ends_with("---\n\nYou review commits.\n"));
+    }
+
+    #[test]
+    fn colliding_native_names_are_rejected() {
+        let mut other = subagent();
+        other.plugin = "commit".to_string();
+        other.name = "commands commit helper".to_string();
+        let err = native_agent_files(&[subagent(), other], render_claude_agent).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("both map to native name commit-commands-commit-helper"));
+        assert!(check_native_names([("a", "b"), ("a-b", "c")]).is_ok());
+    }
+}
@@ -0,0 +1,498 @@
+use super::agents::{
+    native_agent_files, remove_managed_files, render_claude_agent, render_codex_prompt,
+    sync_managed_files,
+};
+use super::changes::{unparseable, ChangeSet};
+use super::hooks::{claude_event, hook_targets, split_event};
+use super::toml_doc::{
+    get_dotted, parse_document, remove_dotted, render_checked, set_dotted, string_array,
+    string_values,
+};
+use super::wrapper::WrapperSpec;
+use super::{Adapter, McpServer, PluginHook, RuntimeView};
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, InlineTable, Item, Table};
+
+/// Markers around the text block older pater versions appended to Codex config.
+const LEGACY_CODEX_BLOCK_START: &str = "# >>> pater managed start >>>";
+const LEGACY_CODEX_BLOCK_END: &str = "# <<< pater managed end <<<";
+
+fn wrapper_for(name: &str, command: &str) -> WrapperSpec {
+    WrapperSpec {
+        name: format!("pater-{}", name),
+        command: command.to_string(),
+        plugin_dir_flag: "--plugin-dir".to_string(),
+    }
+}
+
+pub struct ClaudeAdapter;
+
+fn load_claude_settings(cfg: &Path) -> anyhow::Result<serde_json::Value> {
+    if !cfg.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(&std::fs::read_to_string(cfg)?).map_err(|e| unparseable(cfg, e))
+}
+
+fn mcp_conflict(cfg: &Path, name: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "MCP server {} already exists in {} and is not managed by pater",
+        name,
+        cfg.display()
+    )
+}
+
+fn json_strings(value: Option<&serde_json::Value>) -> Option<Vec<String>> {
+    value.and_then(|v| v.as_array()).map(|a| {
+        a.iter()
+            .filter_map(|x| x.as_str().map(str::to_string))
+            .collect()
+    })
+}
+
+/// Names pater registered on its last sync, from `pater.mcp_servers`.
+fn managed_claude_mcps(settings: &serde_json::Map<String, serde_json::Value>) -> Vec<String> {
+    json_strings(settings.get("pater").and_then(|p| p.get("mcp_servers"))).unwrap_or_default()
+}
+
+fn claude_mcp_entry(server: &McpServer) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "command": server.command,
+        "args": server.args,
+        "env": server.env,
+    });
+    if let Some(cwd) = &server.cwd {
+        entry["cwd"] = serde_json::json!(cwd);
+    }
+    entry
+}
+
+/// Drops `names` from `mcpServers`, and the key itself once it is empty.
+fn remove_claude_mcps(settings: &mut serde_json::Map<String, serde_json::Value>, names: &[String]) {
+    if let Some(servers) = settings
+        .get_mut("mcpServers")
+        .and_then(|s| s.as_object_mut())
+    {
+        for name in names {
+            servers.remove(name);
+        }
+        if servers.is_empty() && !names.is_empty() {
+            settings.remove("mcpServers");
+        }
+    }
+}
+
+/// One `hooks.<Event>` matcher group per plugin hook pater can express.
+fn claude_hook_groups(hooks: &[PluginHook]) -> serde_json::Map<String, serde_json::Value> {
+    let mut out = serde_json::Map::new();
+    for hook in hooks.iter().filter(|h| hook_targets(&h.agent, "claude")) {
+        let (generic, matcher) = split_event(&hook.event);
+        let Some(event) = claude_event(generic) else {
+            continue;
+        };
+        let mut group = serde_json::json!({
+            "hooks": [{ "type": "command", "command": hook.run }],
+        });
+        if let Some(matcher) = matcher {
+            group["matcher"] = serde_json::json!(matcher);
+        }
+        if let Some(groups) = out
+            .entry(event)
+            .or_insert_with(|| serde_json::json!([]))
+            .as_array_mut()
+        {
+            groups.push(group);
+        }
+    }
+    out
+}
+
+/// Removes the groups recorded in `pater.hooks` from `hooks`, pruning event
+/// arrays and the `hooks` key once pater leaves them empty.
+fn remove_claude_hooks(settings: &mut serde_json::Map<String, serde_json::Value>) {
+    let Some(managed) = settings
+        .get("pater")
+        .and_then(|p| p.get("hooks"))
+        .and_then(|h| h.as_object())
+        .cloned()
+    else {
+        return;
+    };
+    let Some(hooks) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) else {
+        return;
+    };
+    for (event, groups) in &managed {
+        let Some(current) = hooks.get_mut(event).and_then(|g| g.as_array_mut()) else {
+            continue;
+        };
+        for group in groups.as_array().into_iter().flatten() {
+            if let Some(pos) = current.iter().position(|g| g == group) {
+                current.remove(pos);
+            }
+        }
+        if current.is_empty() {
+            hooks.remove(event);
+        }
+    }
+    if hooks.is_empty() {
+        settings.remove("hooks");
+    }
+}
+
+impl Adapter for ClaudeAdapter {
+    fn name(&self) -> &str {
+        "claude"
+    }
+
+    fn shim_path(&self, home: &Path) -> PathBuf {
+        home.join(".claude/pater.plugins.json")
+    }
+
+    fn config_file(&self, home: &Path) -> (String, PathBuf) {
+        (
+            "claude_settings".to_string(),
+            home.join(".claude/settings.json"),
+        )
+    }
+
+    fn patch_config(
+        &self,
+        home: &Path,
+        view: &RuntimeView,
+        changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        let cfg = self.config_file(home).1;
+        let mut v = load_claude_settings(&cfg)?;
+        let Some(obj) = v.as_object_mut() else {
+            return Err(unparseable(&cfg, "top level is not an object"));
+        };
+        remove_claude_mcps(obj, &managed_claude_mcps(obj));
+        remove_claude_hooks(obj);
+        if !view.mcp_servers.is_empty() {
+            let servers = obj
+                .entry("mcpServers")
+                .or_insert_with(|| serde_json::json!({}))
+                .as_object_mut()
+                .ok_or_else(|| unparseable(&cfg, "mcpServers is not an object"))?;
+            for server in &view.mcp_servers {
+                if servers.contains_key(&server.name) {
+                    return Err(mcp_conflict(&cfg, &server.name));
+                }
+                servers.insert(server.name.clone(), claude_mcp_entry(server));
+            }
+        }
+        let mut marker = serde_json::json!({ "plugin_dirs": view.plugin_dirs });
+        if !view.mcp_servers.is_empty() {
+            let names = view.mcp_servers.iter().map(|s| &s.name).collect::<Vec<_>>();
+            marker["mcp_servers"] = serde_json::json!(names);
+        }
+        let groups = claude_hook_groups(&view.hooks);
+        if !groups.is_empty() {
+            let hooks = obj
+                .entry("hooks")
+                .or_insert_with(|| serde_json::json!({}))
+                .as_object_mut()
+                .ok_or_else(|| unparseable(&cfg, "hooks is not an object"))?;
+            for (event, new) in &groups {
+                let current = hooks
+                    .entry(event.clone())
+                    .or_insert_with(|| serde_json::json!([]))
+                    .as_array_mut()
+                    .ok_or_else(|| unparseable(&cfg, format!("hooks.{} is not an array", event)))?;
+                current.extend(new.as_array().into_iter().flatten().cloned());
+            }
+            marker["hooks"] = serde_json::Value::Object(groups);
+        }
+        obj.insert("pater".to_string(), marker);
+        changes.write(&cfg, &serde_json::to_string_pretty(&v)?)
+    }
+
+    fn unpatch_config(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+        let cfg = self.config_file(home).1;
+        if !cfg.exists() {
+            return Ok(());
+        }
+        let mut v = load_claude_settings(&cfg)?;
+        if let Some(obj) = v.as_object_mut() {
+            remove_claude_mcps(obj, &managed_claude_mcps(obj));
+            remove_claude_hooks(obj);
+            if obj.remove("pater").is_some() {
+                changes.write(&cfg, &serde_json::to_string_pretty(&v)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_assets(
+        &self,
+        home: &Path,
+        view: &RuntimeView,
+        changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        let files = native_agent_files(&view.subagents, render_claude_agent)?;
+        sync_managed_files(&home.join(".claude/agents"), &files, changes)
+    }
+
+    fn remove_assets(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+        remove_managed_files(&home.join(".claude/agents"), changes)
+    }
+
+    fn skills_dir(&self, home: &Path) -> Option<PathBuf> {
+        Some(home.join(".claude/skills"))
+    }
+
+    fn native_hook_event(&self, event: &str) -> Option<&'static str> {
+        claude_event(event)
+    }
+
+    fn wrapper(&self) -> Option<WrapperSpec> {
+        Some(wrapper_for("claude", "claude"))
+    }
+
+    fn configured_plugin_dirs(&self, home: &Path) -> Option<Vec<String>> {
+        let settings = load_claude_settings(&self.config_file(home).1).ok()?;
+        json_strings(settings.get("pater").and_then(|p| p.get("plugin_dirs")))
+    }
+
+    fn configured_mcp_commands(&self, home: &Path) -> Vec<(String, String)> {
+        let Ok(settings) = load_claude_settings(&self.config_file(home).1) else {
+            return Vec::new();
+        };
+        let Some(obj) = settings.as_object() else {
+            return Vec::new();
+        };
+        managed_claude_mcps(obj)
+            .into_iter()
+            .map(|name| {
+                let command = obj
+                    .get("mcpServers")
+                    .and_then(|m| m.get(&name))
+                    .and_then(|m| m.get("command"))
+                    .and_then(|c| c.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                (name, command)
+            })
+            .collect()
+    }
+}
+
+pub struct CodexAdapter;
+
+/// Keys pater owns in `~/.codex/config.toml`.
+const CODEX_KEY: &str = "pater.plugin_dirs";
+const CODEX_MCPS_KEY: &str = "pater.mcp_servers";
+
+fn managed_codex_mcps(doc: &DocumentMut) -> Vec<String> {
+    get_dotted(doc, CODEX_MCPS_KEY)
+        .and_then(string_values)
+        .unwrap_or_default()
+}
+
+fn codex_mcp_table(server: &McpServer) -> Item {
+    let mut table = Table::new();
+    table.insert("command", toml_edit::value(server.command.as_str()));
+    table.insert("args", string_array(&server.args));
+    if !server.env.is_empty() {
+        let mut env = InlineTable::new();
+        for (k, v) in &server.env {
+            env.insert(k, v.as_str().into());
+        }
+        table.insert("env", toml_edit::value(env));
+    }
+    if let Some(cwd) = &server.cwd {
+        table.insert("cwd", toml_edit::value(cwd.as_str()));
+    }
+    Item::Table(table)
+}
+
+/// Parses the config for editing. Marker-delimited blocks written by older
+/// pater versions are dropped first so they can be rewritten as real TOML.
+fn load_codex_config(cfg: &Path) -> anyhow::Result<DocumentMut> {
+    let mut content = if cfg.exists() {
+        std::fs::read_to_string(cfg)?
+    } else {
+        String::new()
+    };
+    strip_legacy_codex_block(&mut content);
+    parse_document(cfg, &content)
+}
+
+fn strip_legacy_codex_block(content: &mut String) {
+    if let (Some(s), Some(e)) = (
+        content.find(LEGACY_CODEX_BLOCK_START),
+        content.find(LEGACY_CODEX_BLOCK_END),
+    ) {
+        if s < e {
+            let start = content[..s].trim_end_matches('\n').len();
+            let end = e + LEGACY_CODEX_BLOCK_END.len();
+            let end = end + usize::from(content[end..].starts_with('\n'));
+            content.replace_range(start..end, if start == 0 { "" } else { "\n" });
+        }
+    }
+}
+
+impl Adapter for CodexAdapter {
+    fn name(&self) -> &str {
+        "codex"
+    }
+
+    fn shim_path(&self, home: &Path) -> PathBuf {
+        home.join(".codex/pater.plugins.json")
+    }
+
+    fn config_file(&self, home: &Path) -> (String, PathBuf) {
+        ("codex_config".to_string(), home.join(".codex/config.toml"))
+    }
+
+    fn patch_config(
+        &self,
+        home: &Path,
+        view: &RuntimeView,
+        changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        let cfg = self.config_file(home).1;
+        let mut doc = load_codex_config(&cfg)?;
+        let managed = managed_codex_mcps(&doc);
+        let names = view
+            .mcp_servers
+            .iter()
+            .map(|s| s.name.clone())
+            .collect::<Vec<_>>();
+        for name in managed.iter().filter(|n| !names.contains(n)) {
+            remove_dotted(&mut doc, &format!("mcp_servers.{}", name));
+        }
+        for server in &view.mcp_servers {
+            let key = format!("mcp_servers.{}", server.name);
+            let mut item = codex_mcp_table(server);
+            match get_dotted(&doc, &key) {
+                // Keep the table where it is so a resync is a no-op.
+                Some(Item::Table(old)) if managed.contains(&server.name) => {
+                    if let Item::Table(new) = &mut item {
+                        new.set_position(old.position().unwrap_or_default());
+                        *new.decor_mut() = old.decor().clone();
+                    }
+                }
+                Some(_) if managed.contains(&server.name) => {}
+                Some(_) => return Err(mcp_conflict(&cfg, &server.name)),
+                None => {}
+            }
+            set_dotted(&mut doc, &key, item)?;
+        }
+        set_dotted(&mut doc, CODEX_KEY, string_array(&view.plugin_dirs))?;
+        if names.is_empty() {
+            remove_dotted(&mut doc, CODEX_MCPS_KEY);
+        } else {
+            set_dotted(&mut doc, CODEX_MCPS_KEY, string_array(&names))?;
+        }
+        changes.write(&cfg, &render_checked(&cfg, &doc)?)
+    }
+
+    fn unpatch_config(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+        let cfg = self.config_file(home).1;
+        if !cfg.exists() {
+            return Ok(());
+        }
+        let mut doc = load_codex_config(&cfg)?;
+        for name in managed_codex_mcps(&doc) {
+            remove_dotted(&mut doc, &format!("mcp_servers.{}", name));
+        }
+        remove_dotted(&mut doc, CODEX_MCPS_KEY);
+        remove_dotted(&mut doc, CODEX_KEY);
+        changes.write(&cfg, &render_checked(&cfg, &doc)?)
+    }
+
+    /// Codex has no subagents; each becomes a custom prompt (`/<name>`).
+    fn sync_assets(
+        &self,
+        home: &Path,
+        view: &RuntimeView,
+        changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        let files = native_agent_files(&view.subagents, render_codex_prompt)?;
+        sync_managed_files(&home.join(".codex/prompts"), &files, changes)
+    }
+
+    fn remove_assets(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+        remove_managed_files(&home.join(".codex/prompts"), changes)
+    }
+
+    fn skills_dir(&self, home: &Path) -> Option<PathBuf> {
+        Some(home.join(".codex/skills"))
+    }
+
+    fn wrapper(&self) -> Option<WrapperSpec> {
+        Some(wrapper_for("codex", "codex"))
+    }
+
+    fn configured_plugin_dirs(&self, home: &Path) -> Option<Vec<String>> {
+        let doc = load_codex_config(&self.config_file(home).1).ok()?;
+        get_dotted(&doc, CODEX_KEY).and_then(string_values)
+    }
+
+    fn configured_mcp_commands(&self, home: &Path) -> Vec<(String, String)> {
+        let Ok(doc) = load_codex_config(&self.config_file(home).1) else {
+            return Vec::new();
+        };
+        managed_codex_mcps(&doc)
+            .into_iter()
+            .map(|name| {
+                let command = get_dotted(&doc, &format!("mcp_servers.{}.command", name))
+                    .and_then(|c| c.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                (name, command)
+            })
+            .collect()
+    }
+}
+
+pub struct OpenclawAdapter;
+
+impl Adapter for OpenclawAdapter {
+    fn name(&self) -> &str {
+        "openclaw"
+    }
+
+    fn shim_path(&self, home: &Path) -> PathBuf {
+        home.join(".openclaw/workspace/skills/.pater-index.json")
+    }
+
+    /// OpenClaw reads the skills index directly; there is no config to patch.
+    fn config_file(&self, home: &Path) -> (String, PathBuf) {
+        ("openclaw_index".to_string(), self.shim_path(home))
+    }
+
+    fn patch_config(
+        &self,
+        _home: &Path,
+        _view: &RuntimeView,
+        _changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn unpatch_config(&self, _home: &Path, _changes: &mut ChangeSet) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn skills_dir(&self, home: &Path) -> Option<PathBuf> {
+        Some(home.join(".openclaw/workspace/skills"))
+    }
+
+    fn wrapper(&self) -> Option<WrapperSpec> {
+        Some(wrapper_for("openclaw", "openclaw"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_legacy_codex_block;
+
+    #[test]
+    fn legacy_marker_block_is_stripped_cleanly() {
+        let mut content = "model = \"o3\"\n\n# >>> pater managed start >>>\n[pater]\nplugin_dirs = [\"/a\"]\n# <<< pater managed end <<<\n".to_string();
+        strip_legacy_codex_block(&mut content);
+        assert_eq!(content, "model = \"o3\"\n");
+    }
+}
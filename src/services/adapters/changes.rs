@@ -0,0 +1,310 @@
+//! Every file an adapter writes or deletes goes through a `ChangeSet`, which
+//! skips no-op writes, backs up prior agent files, and in dry-run mode only
+//! records unified diffs.
+
+use crate::domain::models::FileChange;
+use crate::services::storage::{adapter_backups_dir, data_base_dir};
+use crate::services::store::{copy_tree, tree_hash, MANAGED_MARKER};
+use similar::TextDiff;
+use std::path::{Component, Path, PathBuf};
+
+/// Backup runs kept under `~/.local/share/pater/backups`.
+const BACKUP_RUNS_KEPT: usize = 10;
+
+pub struct ChangeSet {
+    pub dry_run: bool,
+    home: PathBuf,
+    run_dir: PathBuf,
+    changes: Vec<FileChange>,
+}
+
+fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    let name = path.to_string_lossy();
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(&format!("a{}", name), &format!("b{}", name))
+        .to_string()
+}
+
+impl ChangeSet {
+    pub fn new(home: &Path, dry_run: bool) -> anyhow::Result<Self> {
+        let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+        let run_dir =
+            adapter_backups_dir()?.join(format!("{}-{:09}", ts.as_secs(), ts.subsec_nanos()));
+        Ok(Self {
+            dry_run,
+            home: home.to_path_buf(),
+            run_dir,
+            changes: Vec::new(),
+        })
+    }
+
+    pub fn into_changes(self) -> Vec<FileChange> {
+        self.changes
+    }
+
+    /// Mirrors `path` under this run's backup dir, relative to `$HOME` when
+    /// possible.
+    fn backup_path(&self, path: &Path) -> PathBuf {
+        let rel = path.strip_prefix(&self.home).unwrap_or(path);
+        let rel: PathBuf = rel
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        self.run_dir.join(rel)
+    }
+
+    /// Copies the current file aside unless pater owns it outright.
+    fn backup(&self, path: &Path) -> anyhow::Result<Option<String>> {
+        if !path.exists() || data_base_dir().is_ok_and(|d| path.starts_with(d)) {
+            return Ok(None);
+        }
+        let dst = self.backup_path(path);
+        if !self.dry_run {
+            if let Some(parent) = dst.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(path, &dst)?;
+        }
+        Ok(Some(dst.to_string_lossy().to_string()))
+    }
+
+    fn write_inner(&mut self, path: &Path, content: &str, executable: bool) -> anyhow::Result<()> {
+        let old = if path.exists() {
+            Some(std::fs::read_to_string(path)?)
+        } else {
+            None
+        };
+        if old.as_deref() == Some(content) {
+            return Ok(());
+        }
+        let backup = self.backup(path)?;
+        self.changes.push(FileChange {
+            path: path.to_string_lossy().to_string(),
+            action: if old.is_some() { "modify" } else { "create" }.to_string(),
+            diff: unified_diff(path, old.as_deref().unwrap_or(""), content),
+            backup,
+        });
+        if self.dry_run {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        #[cfg(unix)]
+        if executable {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(path)?.permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(path, perms)?;
+        }
+        #[cfg(not(unix))]
+        let _ = executable;
+        Ok(())
+    }
+
+    pub fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+        self.write_inner(path, content, false)
+    }
+
+    /// Like `write`, but marks the file executable (wrapper scripts).
+    pub fn write_script(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+        self.write_inner(path, content, true)
+    }
+
+    pub fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let old = std::fs::read_to_string(path).unwrap_or_default();
+        let backup = self.backup(path)?;
+        self.changes.push(FileChange {
+            path: path.to_string_lossy().to_string(),
+            action: "delete".to_string(),
+            diff: unified_diff(path, &old, ""),
+            backup,
+        });
+        if !self.dry_run {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Makes `path` a symlink to the directory `target`. Where symlinks are
+    /// unavailable, copies the tree instead and leaves `MANAGED_MARKER` in it.
+    /// A link already pointing at `target`, or an up-to-date copy, is left alone.
+    pub fn link_dir(&mut self, path: &Path, target: &Path) -> anyhow::Result<()> {
+        if std::fs::read_link(path).is_ok_and(|t| t == target)
+            || copy_marker(path).is_some_and(|m| copy_marker_content(target).is_ok_and(|c| m == c))
+        {
+            return Ok(());
+        }
+        self.remove_tree(path)?;
+        self.changes.push(FileChange {
+            path: path.to_string_lossy().to_string(),
+            action: "link".to_string(),
+            diff: format!("+{} -> {}\n", path.display(), target.display()),
+            backup: None,
+        });
+        if self.dry_run {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if symlink_dir(target, path).is_err() {
+            copy_tree(target, path)?;
+            std::fs::write(path.join(MANAGED_MARKER), copy_marker_content(target)?)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a symlink or a directory tree written by `link_dir`.
+    pub fn remove_tree(&mut self, path: &Path) -> anyhow::Result<()> {
+        let Ok(meta) = std::fs::symlink_metadata(path) else {
+            return Ok(());
+        };
+        self.changes.push(FileChange {
+            path: path.to_string_lossy().to_string(),
+            action: "unlink".to_string(),
+            diff: format!("-{}\n", path.display()),
+            backup: None,
+        });
+        if self.dry_run {
+            return Ok(());
+        }
+        if meta.file_type().is_symlink() {
+            // Windows directory symlinks are removed as directories.
+            std::fs::remove_file(path).or_else(|_| std::fs::remove_dir(path))?;
+        } else if meta.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Drops backup runs beyond `BACKUP_RUNS_KEPT`, oldest first.
+    pub fn prune_backups(&self) -> anyhow::Result<()> {
+        let root = adapter_backups_dir()?;
+        if self.dry_run || !root.exists() {
+            return Ok(());
+        }
+        let mut runs = std::fs::read_dir(&root)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_dir())
+            .collect::<Vec<_>>();
+        runs.sort();
+        let excess = runs.len().saturating_sub(BACKUP_RUNS_KEPT);
+        for run in runs.into_iter().take(excess) {
+            std::fs::remove_dir_all(run)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, path)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink_dir(_target: &Path, _path: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Records the source and its store hash, so a copy is redone when the
+/// source changes.
+fn copy_marker_content(target: &Path) -> anyhow::Result<String> {
+    Ok(format!("{}\n{}\n", target.display(), tree_hash(target)?))
+}
+
+/// Content of `MANAGED_MARKER` in `dir`, if `dir` is a real directory holding one.
+pub fn copy_marker(dir: &Path) -> Option<String> {
+    let meta = std::fs::symlink_metadata(dir).ok()?;
+    if !meta.is_dir() {
+        return None;
+    }
+    std::fs::read_to_string(dir.join(MANAGED_MARKER)).ok()
+}
+
+/// Error for agent configs pater cannot parse; they are never overwritten.
+pub fn unparseable(path: &Path, err: impl std::fmt::Display) -> anyhow::Error {
+    anyhow::anyhow!(
+        "refusing to modify unparseable config {}: {}",
+        path.display(),
+        err
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_records_diffs_without_touching_files() {
+        let tmp = tempfile::TempDir::new().expect("tmp");
+        let file = tmp.path().join("settings.json");
+        std::fs::write(&file, "{\n  \"a\": 1\n}\n").expect("seed");
+
+        let mut changes = ChangeSet::new(tmp.path(), true).expect("changeset");
+        changes
+            .write(&file, "{\n  \"a\": 2\n}\n")
+            .expect("planned write");
+        changes
+            .write(&tmp.path().join("new.txt"), "x\n")
+            .expect("planned create");
+        let out = changes.into_changes();
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].action, "modify");
+        assert!(out[0].diff.contains("-  \"a\": 1"));
+        assert!(out[0].diff.contains("+  \"a\": 2"));
+        assert_eq!(out[1].action, "create");
+        assert_eq!(
+            std::fs::read_to_string(&file).expect("read"),
+            "{\n  \"a\": 1\n}\n"
+        );
+        assert!(!tmp.path().join("new.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn link_dir_is_idempotent_and_remove_tree_drops_the_link() {
+        let tmp = tempfile::TempDir::new().expect("tmp");
+        let target = tmp.path().join("runtime/skill");
+        std::fs::create_dir_all(&target).expect("target");
+        let link = tmp.path().join("skills/p-skill");
+
+        let mut changes = ChangeSet::new(tmp.path(), false).expect("changeset");
+        changes.link_dir(&link, &target).expect("link");
+        changes.link_dir(&link, &target).expect("relink");
+        assert_eq!(std::fs::read_link(&link).expect("is link"), target);
+        changes.remove_tree(&link).expect("unlink");
+        assert!(std::fs::symlink_metadata(&link).is_err());
+        assert!(target.exists());
+
+        let actions = changes
+            .into_changes()
+            .into_iter()
+            .map(|c| c.action)
+            .collect::<Vec<_>>();
+        assert_eq!(actions, ["link", "unlink"]);
+    }
+
+    #[test]
+    fn unchanged_content_is_not_a_change() {
+        let tmp = tempfile::TempDir::new().expect("tmp");
+        let file = tmp.path().join("same.txt");
+        std::fs::write(&file, "same\n").expect("seed");
+        let mut changes = ChangeSet::new(tmp.path(), true).expect("changeset");
+        changes.write(&file, "same\n").expect("write");
+        assert!(changes.into_changes().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+/// On-disk format of an agent config file. Files are edited through a
+/// `serde_json::Value` so key patching is format-independent.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+pub fn parse(format: ConfigFormat, raw: &str) -> anyhow::Result<Value> {
+    if raw.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    let value = match format {
+        ConfigFormat::Json => serde_json::from_str(raw)?,
+        ConfigFormat::Toml => serde_json::to_value(toml::from_str::<toml::Value>(raw)?)?,
+        ConfigFormat::Yaml => {
+            serde_json::to_value(serde_yaml::from_str::<serde_yaml::Value>(raw)?)?
+        }
+    };
+    Ok(value)
+}
+
+pub fn render(format: ConfigFormat, value: &Value) -> anyhow::Result<String> {
+    Ok(match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(value)?,
+        ConfigFormat::Toml => {
+            toml::to_string(&serde_json::from_value::<toml::Value>(value.clone())?)?
+        }
+        ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+    })
+}
+
+/// Sets a dotted `key` (e.g. `extensions.pater`), creating parent tables.
+pub fn set_key(root: &mut Value, key: &str, value: Value) -> anyhow::Result<()> {
+    let mut cur = root;
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let Some(obj) = cur.as_object_mut() else {
+            anyhow::bail!("cannot set config key {}: {} is not a table", key, part);
+        };
+        if parts.peek().is_none() {
+            obj.insert(part.to_string(), value);
+            return Ok(());
+        }
+        cur = obj
+            .entry(part.to_string())
+            .or_insert_with(|| serde_json::json!({}));
+    }
+    Ok(())
+}
+
+pub fn get_key<'a>(root: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(root, |cur, part| cur.get(part))
+}
+
+/// Removes a dotted `key`, then any parent tables it leaves empty. Returns
+/// whether anything was removed.
+pub fn remove_key(root: &mut Value, key: &str) -> bool {
+    fn remove_in(cur: &mut Value, parts: &[&str]) -> bool {
+        let Some(obj) = cur.as_object_mut() else {
+            return false;
+        };
+        match parts {
+            [] => false,
+            [leaf] => obj.remove(*leaf).is_some(),
+            [head, rest @ ..] => {
+                let Some(child) = obj.get_mut(*head) else {
+                    return false;
+                };
+                let removed = remove_in(child, rest);
+                if removed && child.as_object().is_some_and(|c| c.is_empty()) {
+                    obj.remove(*head);
+                }
+                removed
+            }
+        }
+    }
+    let parts = key.split('.').collect::<Vec<_>>();
+    remove_in(root, &parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotted_keys_round_trip_through_each_format() {
+        for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+            let mut v = parse(format, "").expect("empty parses");
+            set_key(
+                &mut v,
+                "extensions.pater",
+                serde_json::json!({"plugin_dirs": ["/a"]}),
+            )
+            .expect("set key");
+            let raw = render(format, &v).expect("render");
+            let mut back = parse(format, &raw).expect("reparse");
+            assert_eq!(
+                get_key(&back, "extensions.pater.plugin_dirs"),
+                Some(&serde_json::json!(["/a"]))
+            );
+            assert!(remove_key(&mut back, "extensions.pater.plugin_dirs"));
+            assert!(get_key(&back, "extensions").is_none());
+        }
+    }
+
+    #[test]
+    fn set_key_refuses_to_descend_into_scalars() {
+        let mut v = serde_json::json!({"extensions": 1});
+        assert!(set_key(&mut v, "extensions.pater", Value::Null).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use super::changes::{unparseable, ChangeSet};
+use super::config_format::{self, ConfigFormat};
+use super::toml_doc::{
+    get_dotted, parse_document, remove_dotted, render_checked, set_dotted, string_array,
+    string_values,
+};
+use super::wrapper::WrapperSpec;
+use super::{Adapter, RuntimeView};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+/// One `~/.config/pater/adapters/<file>.toml` entry.
+///
+/// ```toml
+/// name = "harness"
+/// config_path = "~/.harness/settings.yaml"
+/// format = "yaml"
+/// key = "extensions.pater"
+/// wrapper_command = "harness --profile work {plugin_args}"
+/// plugin_dir_flag = "--extension-dir={dir}"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdapterSpec {
+    pub name: String,
+    /// Agent config file; `~/` and relative paths resolve against `$HOME`.
+    pub config_path: String,
+    pub format: ConfigFormat,
+    /// Dotted table whose `plugin_dirs` pater owns; other settings under it
+    /// are left alone.
+    #[serde(default = "default_key")]
+    pub key: String,
+    /// Command the `pater-<name>` wrapper execs. `{plugin_args}` marks where
+    /// plugin-dir flags go; they are appended when it is absent.
+    pub wrapper_command: Option<String>,
+    /// Per-dir flag; `{dir}` is replaced by the quoted runtime path,
+    /// otherwise the path follows the flag as a separate argument.
+    #[serde(default = "default_plugin_dir_flag")]
+    pub plugin_dir_flag: String,
+    /// Shim index location; defaults to `pater.plugins.json` next to the config.
+    pub shim_path: Option<String>,
+    /// Native skills dir plugin skills are linked into; none by default.
+    pub skills_dir: Option<String>,
+}
+
+fn default_key() -> String {
+    "pater".to_string()
+}
+
+fn default_plugin_dir_flag() -> String {
+    "--plugin-dir".to_string()
+}
+
+fn resolve_home_path(home: &Path, raw: &str) -> PathBuf {
+    match raw.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => home.join(raw),
+    }
+}
+
+pub struct DeclarativeAdapter {
+    spec: AdapterSpec,
+}
+
+impl DeclarativeAdapter {
+    fn config_path(&self, home: &Path) -> PathBuf {
+        resolve_home_path(home, &self.spec.config_path)
+    }
+
+    fn plugin_dirs_key(&self) -> String {
+        format!("{}.plugin_dirs", self.spec.key)
+    }
+
+    fn load_config(&self, path: &Path) -> anyhow::Result<serde_json::Value> {
+        let raw = if path.exists() {
+            std::fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        config_format::parse(self.spec.format, &raw).map_err(|e| unparseable(path, e))
+    }
+
+    /// TOML configs are edited in place so comments and layout survive.
+    fn load_toml(&self, path: &Path) -> anyhow::Result<DocumentMut> {
+        let raw = if path.exists() {
+            std::fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        parse_document(path, &raw)
+    }
+}
+
+impl Adapter for DeclarativeAdapter {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn shim_path(&self, home: &Path) -> PathBuf {
+        match &self.spec.shim_path {
+            Some(p) => resolve_home_path(home, p),
+            None => self.config_path(home).with_file_name("pater.plugins.json"),
+        }
+    }
+
+    fn skills_dir(&self, home: &Path) -> Option<PathBuf> {
+        self.spec
+            .skills_dir
+            .as_deref()
+            .map(|p| resolve_home_path(home, p))
+    }
+
+    fn config_file(&self, home: &Path) -> (String, PathBuf) {
+        (format!("{}_config", self.spec.name), self.config_path(home))
+    }
+
+    fn patch_config(
+        &self,
+        home: &Path,
+        view: &RuntimeView,
+        changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        let path = self.config_path(home);
+        if self.spec.format == ConfigFormat::Toml {
+            let mut doc = self.load_toml(&path)?;
+            set_dotted(
+                &mut doc,
+                &self.plugin_dirs_key(),
+                string_array(&view.plugin_dirs),
+            )?;
+            return changes.write(&path, &render_checked(&path, &doc)?);
+        }
+        let mut v = self.load_config(&path)?;
+        config_format::set_key(
+            &mut v,
+            &self.plugin_dirs_key(),
+            serde_json::json!(view.plugin_dirs),
+        )?;
+        changes.write(&path, &config_format::render(self.spec.format, &v)?)
+    }
+
+    fn unpatch_config(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+        let path = self.config_path(home);
+        if !path.exists() {
+            return Ok(());
+        }
+        if self.spec.format == ConfigFormat::Toml {
+            let mut doc = self.load_toml(&path)?;
+            if remove_dotted(&mut doc, &self.plugin_dirs_key()) {
+                changes.write(&path, &render_checked(&path, &doc)?)?;
+            }
+            return Ok(());
+        }
+        let mut v = self.load_config(&path)?;
+        if config_format::remove_key(&mut v, &self.plugin_dirs_key()) {
+            changes.write(&path, &config_format::render(self.spec.format, &v)?)?;
+        }
+        Ok(())
+    }
+
+    fn wrapper(&self) -> Option<WrapperSpec> {
+        self.spec
+            .wrapper_command
+            .as_ref()
+            .map(|command| WrapperSpec {
+                name: format!("pater-{}", self.spec.name),
+                command: command.clone(),
+                plugin_dir_flag: self.spec.plugin_dir_flag.clone(),
+            })
+    }
+
+    fn configured_plugin_dirs(&self, home: &Path) -> Option<Vec<String>> {
+        let path = self.config_path(home);
+        let key = self.plugin_dirs_key();
+        if self.spec.format == ConfigFormat::Toml {
+            let doc = self.load_toml(&path).ok()?;
+            return get_dotted(&doc, &key).and_then(string_values);
+        }
+        let v = self.load_config(&path).ok()?;
+        let dirs = config_format::get_key(&v, &key)?.as_array()?;
+        Some(
+            dirs.iter()
+                .filter_map(|d| d.as_str().map(str::to_string))
+                .collect(),
+        )
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Loads `*.toml` adapter specs from `dir` in file-name order. Names must be
+/// unique and must not shadow `reserved` (built-ins and `all`).
+pub fn load_declarative_adapters(
+    dir: &Path,
+    reserved: &[String],
+) -> anyhow::Result<Vec<DeclarativeAdapter>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "toml"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut seen: Vec<String> = reserved.to_vec();
+    let mut out = Vec::new();
+    for file in files {
+        let raw = std::fs::read_to_string(&file)?;
+        let spec: AdapterSpec = toml::from_str(&raw)
+            .map_err(|e| anyhow::anyhow!("invalid adapter file {}: {}", file.display(), e))?;
+        if !valid_name(&spec.name) {
+            anyhow::bail!(
+                "invalid adapter file {}: name must match [a-z0-9_-]+",
+                file.display()
+            );
+        }
+        if seen.contains(&spec.name) {
+            anyhow::bail!(
+                "invalid adapter file {}: adapter name {} is already taken",
+                file.display(),
+                spec.name
+            );
+        }
+        seen.push(spec.name.clone());
+        out.push(DeclarativeAdapter { spec });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_load_in_order_and_reject_reserved_names() {
+        let tmp = tempfile::TempDir::new().expect("tmp");
+        std::fs::write(
+            tmp.path().join("b.toml"),
+            "name = \"beta\"\nconfig_path = \"~/.beta/config.json\"\nformat = \"json\"\n",
+        )
+        .expect("write b");
+        std::fs::write(
+            tmp.path().join("a.toml"),
+            "name = \"alpha\"\nconfig_path = \".alpha.yaml\"\nformat = \"yaml\"\nkey = \"ext.pater\"\n",
+        )
+        .expect("write a");
+        std::fs::write(tmp.path().join("notes.txt"), "ignored").expect("write txt");
+
+        let loaded = load_declarative_adapters(tmp.path(), &["all".to_string()]).expect("load");
+        let names: Vec<&str> = loaded.iter().map(|a| a.name()).collect();
+        assert_eq!(names, vec!["alpha", "beta"]);
+        assert_eq!(
+            loaded[1].shim_path(Path::new("/h")),
+            PathBuf::from("/h/.beta/pater.plugins.json")
+        );
+
+        let err = load_declarative_adapters(tmp.path(), &["beta".to_string()])
+            .err()
+            .expect("reserved name rejected");
+        assert!(err.to_string().contains("already taken"));
+    }
+}
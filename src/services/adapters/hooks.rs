@@ -0,0 +1,99 @@
+//! Pater's generic hook events and how adapters map them to native ones.
+//!
+//! A hook's `event` is a generic name, optionally followed by `:<matcher>`
+//! for agents that filter hooks by tool (`pre-tool:Bash|Edit`).
+
+use super::{Adapter, PluginHook};
+use crate::cli::ALL_ADAPTERS;
+use crate::domain::models::SkippedHook;
+
+/// Event names plugins may use in `hooks[].event`.
+pub const HOOK_EVENTS: &[&str] = &[
+    "session-start",
+    "session-end",
+    "prompt-submit",
+    "pre-tool",
+    "post-tool",
+    "notification",
+    "stop",
+    "subagent-stop",
+    "pre-compact",
+];
+
+/// Splits `pre-tool:Bash` into `("pre-tool", Some("Bash"))`.
+pub fn split_event(event: &str) -> (&str, Option<&str>) {
+    match event.split_once(':') {
+        Some((name, matcher)) if !matcher.is_empty() => (name, Some(matcher)),
+        Some((name, _)) => (name, None),
+        None => (event, None),
+    }
+}
+
+/// Whether a hook's `agent` field selects `adapter` (`all` and `*` select every adapter).
+pub fn hook_targets(hook_agent: &str, adapter: &str) -> bool {
+    hook_agent == adapter || hook_agent == ALL_ADAPTERS || hook_agent == "*"
+}
+
+/// Claude Code names for the generic events; every generic event has one.
+pub fn claude_event(generic: &str) -> Option<&'static str> {
+    Some(match generic {
+        "session-start" => "SessionStart",
+        "session-end" => "SessionEnd",
+        "prompt-submit" => "UserPromptSubmit",
+        "pre-tool" => "PreToolUse",
+        "post-tool" => "PostToolUse",
+        "notification" => "Notification",
+        "stop" => "Stop",
+        "subagent-stop" => "SubagentStop",
+        "pre-compact" => "PreCompact",
+        _ => return None,
+    })
+}
+
+/// Hooks aimed at `adapter` that it cannot express, with the reason.
+pub fn skipped_hooks(adapter: &dyn Adapter, hooks: &[PluginHook]) -> Vec<SkippedHook> {
+    hooks
+        .iter()
+        .filter(|h| hook_targets(&h.agent, adapter.name()))
+        .filter_map(|h| {
+            let (generic, _) = split_event(&h.event);
+            let reason = if !HOOK_EVENTS.contains(&generic) {
+                format!(
+                    "unknown event (expected one of: {})",
+                    HOOK_EVENTS.join(", ")
+                )
+            } else if adapter.native_hook_event(generic).is_none() {
+                format!("{} has no native equivalent", adapter.name())
+            } else {
+                return None;
+            };
+            Some(SkippedHook {
+                adapter: adapter.name().to_string(),
+                plugin: h.plugin.clone(),
+                event: h.event.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_split_matchers_and_map_to_claude() {
+        assert_eq!(
+            split_event("pre-tool:Bash|Edit"),
+            ("pre-tool", Some("Bash|Edit"))
+        );
+        assert_eq!(split_event("stop"), ("stop", None));
+        assert_eq!(split_event("stop:"), ("stop", None));
+        for event in HOOK_EVENTS {
+            assert!(claude_event(event).is_some(), "{} unmapped", event);
+        }
+        assert_eq!(claude_event("on-demand"), None);
+        assert!(hook_targets("all", "codex"));
+        assert!(!hook_targets("claude", "codex"));
+    }
+}
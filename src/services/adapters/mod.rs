@@ -0,0 +1,343 @@
+//! Agent adapters: how installed runtime plugins are exposed to agent CLIs.
+//!
+//! ## Files
+//! - `mod.rs` — `Adapter` trait, adapter registry, sync/smoke/doctor/unsync flows.
+//! - `agents.rs` — subagent definition files (Claude agents, Codex prompts).
+//! - `builtin.rs` — Claude, Codex and OpenClaw implementations.
+//! - `declarative.rs` — adapters described in `~/.config/pater/adapters/*.toml`.
+//! - `config_format.rs` — json/toml/yaml config parsing + dotted-key patching.
+//! - `runtime_registry.rs` — runtime materialization + `registry.json` assembly.
+//! - `hooks.rs` — generic hook events and their per-agent names.
+//! - `wrapper.rs` — `~/.local/bin/pater-*` launcher scripts (sh, or `.cmd`/`.ps1` on Windows).
+//! - `originals.rs` — pre-pater config snapshots restored on unsync.
+//! - `changes.rs` — `ChangeSet`: backed-up writes and dry-run diffs.
+//! - `skills.rs` — plugin skills linked into agents' native skills dirs.
+//! - `smoke.rs` — `adapter smoke`: synced files checked against the registry.
+//! - `toml_doc.rs` — comment-preserving TOML edits (Codex, declarative toml).
+//!
+//! ## Adding an agent
+//! Most agents need no code: drop a declarative spec into
+//! `~/.config/pater/adapters/`. Agents with bespoke config handling implement
+//! `Adapter` and register it in `builtin_adapters`. Everything else
+//! (`--target <name>`, bridges, smoke, doctor, unsync) is driven by the trait.
+
+mod agents;
+mod builtin;
+mod changes;
+mod config_format;
+mod declarative;
+mod hooks;
+mod originals;
+mod runtime_registry;
+mod skills;
+mod smoke;
+mod toml_doc;
+mod wrapper;
+
+use crate::cli::ALL_ADAPTERS;
+use crate::domain::models::{CheckItem, DoctorReport, PolicyFile, State, SyncReport, UnsyncItem};
+use crate::services::storage::{
+    adapter_specs_dir, home_dir, runtime_bridges_dir, runtime_registry_path,
+};
+use builtin::{ClaudeAdapter, CodexAdapter, OpenclawAdapter};
+use changes::ChangeSet;
+use declarative::load_declarative_adapters;
+use hooks::skipped_hooks;
+use originals::{capture_original, record_written, restore_original, RestoreOutcome};
+use runtime_registry::build_runtime_registry;
+pub use runtime_registry::{McpServer, PluginHook, PluginSkill, PluginSubagent, RuntimeView};
+use skills::{link_skills, unlink_skills};
+pub use smoke::adapter_smoke;
+use std::path::{Path, PathBuf};
+pub use wrapper::WrapperSpec;
+use wrapper::{remove_wrapper, wrapper_bin_dir, wrapper_path, write_wrapper};
+
+pub trait Adapter {
+    /// Stable name used for `--target`, bridge files and reports.
+    fn name(&self) -> &str;
+
+    /// Index file pater writes for the agent, pointing at runtime paths.
+    fn shim_path(&self, home: &Path) -> PathBuf;
+
+    /// Native config file the agent reads, as `(doctor check name, path)`.
+    fn config_file(&self, home: &Path) -> (String, PathBuf);
+
+    /// Writes pater's entries into the agent's native config. Must fail
+    /// rather than overwrite a config it cannot parse.
+    fn patch_config(
+        &self,
+        home: &Path,
+        view: &RuntimeView,
+        changes: &mut ChangeSet,
+    ) -> anyhow::Result<()>;
+
+    /// Removes pater's entries from the agent's native config.
+    fn unpatch_config(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<()>;
+
+    /// Writes files pater owns outright in the agent's dirs (subagent
+    /// definitions), removing ones left from earlier syncs.
+    fn sync_assets(
+        &self,
+        _home: &Path,
+        _view: &RuntimeView,
+        _changes: &mut ChangeSet,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Removes everything `sync_assets` wrote. Runs on every unsync, since
+    /// these files are not covered by the config snapshot.
+    fn remove_assets(&self, _home: &Path, _changes: &mut ChangeSet) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Native skills dir plugin skills are linked into, if the agent has one.
+    fn skills_dir(&self, _home: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Native name for a generic hook event (see `hooks.rs`), or `None` if
+    /// the agent cannot express it. `patch_config` writes only mapped hooks.
+    fn native_hook_event(&self, _event: &str) -> Option<&'static str> {
+        None
+    }
+
+    /// Launcher script installed into `~/.local/bin`, if the agent has one.
+    fn wrapper(&self) -> Option<WrapperSpec>;
+
+    /// Plugin dirs pater's entry in the agent config points at, or `None`
+    /// if the entry is missing or the config unreadable. Smoke compares them
+    /// with the registry. Defaults to the shim, for agents that read it directly.
+    fn configured_plugin_dirs(&self, home: &Path) -> Option<Vec<String>> {
+        smoke::json_plugin_dirs(&self.shim_path(home))
+    }
+
+    /// MCP servers pater registered in the agent config, as `(name, command)`.
+    fn configured_mcp_commands(&self, _home: &Path) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn sync(&self, home: &Path, view: &RuntimeView, changes: &mut ChangeSet) -> anyhow::Result<()> {
+        let bridge_data = serde_json::json!({
+            "managedBy": "pater",
+            "adapter": self.name(),
+            "runtime_registry": view.registry_path,
+            "plugins": view.plugins.iter().map(|p| &p.key).collect::<Vec<_>>(),
+            "plugin_dirs": view.plugin_dirs,
+            "note": "Runtime-first bridge generated by pater. Agent config should read runtime paths.",
+        });
+        let body = serde_json::to_string_pretty(&bridge_data)?;
+
+        // The first sync snapshots the agent config so unsync can restore it.
+        let config_path = self.config_file(home).1;
+        let tracked = config_path != self.shim_path(home) && !changes.dry_run;
+        if tracked {
+            capture_original(self.name(), &config_path)?;
+        }
+
+        // Config first: an unparseable config aborts before anything is written.
+        self.patch_config(home, view, changes)?;
+        if tracked {
+            record_written(self.name(), &config_path)?;
+        }
+        self.sync_assets(home, view, changes)?;
+        if let Some(dir) = self.skills_dir(home) {
+            link_skills(&dir, &view.skills, changes)?;
+        }
+        for path in [self.shim_path(home), bridge_file_path(self.name())?] {
+            changes.write(&path, &body)?;
+        }
+        if let Some(spec) = self.wrapper() {
+            write_wrapper(home, &spec, &view.plugin_dirs, changes)?;
+        }
+        Ok(())
+    }
+
+    /// Reverts everything `sync` wrote for this adapter. The agent config is
+    /// restored byte-for-byte from its snapshot unless it was edited after
+    /// pater's last write, in which case only pater's entries are removed.
+    /// Returns `restored` or `unpatched`.
+    fn unsync(&self, home: &Path, changes: &mut ChangeSet) -> anyhow::Result<String> {
+        if let Some(spec) = self.wrapper() {
+            remove_wrapper(home, &spec, changes)?;
+        }
+        for path in [self.shim_path(home), bridge_file_path(self.name())?] {
+            changes.remove(&path)?;
+        }
+        self.remove_assets(home, changes)?;
+        if let Some(dir) = self.skills_dir(home) {
+            unlink_skills(&dir, changes)?;
+        }
+        match restore_original(self.name(), changes)? {
+            RestoreOutcome::Restored => Ok("restored".to_string()),
+            RestoreOutcome::ModifiedSince | RestoreOutcome::NoSnapshot => {
+                self.unpatch_config(home, changes)?;
+                Ok("unpatched".to_string())
+            }
+        }
+    }
+}
+
+fn builtin_adapters() -> Vec<Box<dyn Adapter>> {
+    vec![
+        Box::new(ClaudeAdapter),
+        Box::new(CodexAdapter),
+        Box::new(OpenclawAdapter),
+    ]
+}
+
+/// All adapters pater knows about, in report order: built-ins first, then
+/// declarative adapters by file name.
+pub fn adapter_registry() -> anyhow::Result<Vec<Box<dyn Adapter>>> {
+    let mut registry = builtin_adapters();
+    let mut reserved = vec![ALL_ADAPTERS.to_string()];
+    reserved.extend(registry.iter().map(|a| a.name().to_string()));
+    for adapter in load_declarative_adapters(&adapter_specs_dir()?, &reserved)? {
+        registry.push(Box::new(adapter));
+    }
+    Ok(registry)
+}
+
+pub fn adapter_names() -> anyhow::Result<Vec<String>> {
+    Ok(adapter_registry()?
+        .iter()
+        .map(|a| a.name().to_string())
+        .collect())
+}
+
+/// Resolves `--target`/`--agent` to adapters; `all` selects every adapter.
+pub fn resolve_adapters(target: &str) -> anyhow::Result<Vec<Box<dyn Adapter>>> {
+    let registry = adapter_registry()?;
+    if target == ALL_ADAPTERS {
+        return Ok(registry);
+    }
+    let known = registry
+        .iter()
+        .map(|a| a.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let selected: Vec<_> = registry
+        .into_iter()
+        .filter(|a| a.name() == target)
+        .collect();
+    if selected.is_empty() {
+        anyhow::bail!("unknown adapter: {} (known: all, {})", target, known);
+    }
+    Ok(selected)
+}
+
+fn check_exists(name: &str, path: PathBuf) -> CheckItem {
+    CheckItem {
+        name: name.to_string(),
+        status: if path.exists() { "ok" } else { "missing" }.to_string(),
+    }
+}
+
+fn bridge_file_path(adapter: &str) -> anyhow::Result<PathBuf> {
+    Ok(runtime_bridges_dir()?.join(format!("{}.json", adapter)))
+}
+
+/// Checks adapters a plugin is pinned to: each must be a registered adapter
+/// (not `all`) that the plugin's `agents` metadata allows.
+pub fn check_pin(plugin: &str, agents: &[String], pins: &[String]) -> anyhow::Result<()> {
+    for pin in pins {
+        if pin == ALL_ADAPTERS {
+            anyhow::bail!("cannot pin {} to all; use `pater adapter unpin`", plugin);
+        }
+        resolve_adapters(pin)?;
+        if !agents.is_empty() && !agents.contains(pin) {
+            anyhow::bail!(
+                "plugin {} does not support agent {} (supports: {})",
+                plugin,
+                pin,
+                agents.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn sync_installed(state: &State, policy: &PolicyFile, target: &str) -> anyhow::Result<()> {
+    sync_adapters(state, policy, target, false).map(|_| ())
+}
+
+/// Syncs `target` and returns every file touched plus the hooks no adapter
+/// could express. With `dry_run` nothing is written and the returned
+/// changes carry the diffs that would apply.
+pub fn sync_adapters(
+    state: &State,
+    policy: &PolicyFile,
+    target: &str,
+    dry_run: bool,
+) -> anyhow::Result<SyncReport> {
+    let adapters = resolve_adapters(target)?;
+    let home = home_dir()?;
+    let mut changes = ChangeSet::new(&home, dry_run)?;
+    let view = build_runtime_registry(state, policy, &mut changes)?;
+    let mut skipped = Vec::new();
+    let mut skipped_plugins = Vec::new();
+    for adapter in adapters {
+        let view = view.for_adapter(adapter.name());
+        adapter.sync(&home, &view, &mut changes)?;
+        skipped.extend(skipped_hooks(adapter.as_ref(), &view.hooks));
+        skipped_plugins.extend(view.skipped_plugins);
+    }
+    changes.prune_backups()?;
+    Ok(SyncReport {
+        changes: changes.into_changes(),
+        skipped_hooks: skipped,
+        skipped_plugins,
+    })
+}
+
+pub fn unsync_adapters(target: &str) -> anyhow::Result<Vec<UnsyncItem>> {
+    let home = home_dir()?;
+    let mut changes = ChangeSet::new(&home, false)?;
+    let mut out = Vec::new();
+    for adapter in resolve_adapters(target)? {
+        let config = adapter.unsync(&home, &mut changes)?;
+        out.push(UnsyncItem {
+            adapter: adapter.name().to_string(),
+            config,
+        });
+    }
+    changes.prune_backups()?;
+    Ok(out)
+}
+
+pub fn adapter_doctor(state: &State) -> anyhow::Result<DoctorReport> {
+    let home = home_dir()?;
+    let smoke = adapter_smoke(state, ALL_ADAPTERS)?;
+    let adapters = adapter_registry()?;
+
+    let mut configs: Vec<CheckItem> = adapters
+        .iter()
+        .map(|a| {
+            let (name, path) = a.config_file(&home);
+            check_exists(&name, path)
+        })
+        .collect();
+    configs.push(check_exists("runtime_registry", runtime_registry_path()?));
+
+    let wrappers = adapters
+        .iter()
+        .filter_map(|a| a.wrapper())
+        .map(|spec| check_exists(&spec.name, wrapper_path(&home, &spec)))
+        .collect::<Vec<_>>();
+
+    let local_bin = wrapper_bin_dir(&home);
+    let path_has_local_bin = std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|p| p == local_bin));
+
+    let all_ok = smoke.iter().all(|s| s.status == "ok")
+        && configs.iter().all(|c| c.status == "ok")
+        && wrappers.iter().all(|w| w.status == "ok");
+
+    Ok(DoctorReport {
+        overall: if all_ok { "ok" } else { "needs_attention" }.to_string(),
+        path_has_local_bin,
+        smoke,
+        configs,
+        wrappers,
+    })
+}
@@ -0,0 +1,130 @@
+//! Pre-pater snapshots of agent config files, so unsync can put them back
+//! byte-for-byte.
+
+use super::changes::ChangeSet;
+use crate::services::storage::adapter_originals_dir;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize)]
+struct OriginalConfig {
+    path: PathBuf,
+    /// File content before pater first patched it; `None` if it did not exist.
+    content: Option<String>,
+    /// Whether the file's parent dir existed before pater created it.
+    parent_existed: bool,
+    /// Hash of the last content pater wrote, to detect later user edits.
+    written_sha256: Option<String>,
+    /// Set when a sync found the file changed since pater's previous write.
+    /// The snapshot then predates those edits and must not be restored.
+    #[serde(default)]
+    edited_between_syncs: bool,
+}
+
+pub enum RestoreOutcome {
+    /// File is back to its pre-pater bytes (or gone, if pater created it).
+    Restored,
+    /// File changed after one of pater's writes; caller should unpatch in place.
+    ModifiedSince,
+    /// No snapshot exists (file patched by an older pater).
+    NoSnapshot,
+}
+
+fn record_path(adapter: &str) -> anyhow::Result<PathBuf> {
+    Ok(adapter_originals_dir()?.join(format!("{}.json", adapter)))
+}
+
+fn load_record(adapter: &str) -> anyhow::Result<Option<OriginalConfig>> {
+    let path = record_path(adapter)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+}
+
+fn save_record(adapter: &str, record: &OriginalConfig) -> anyhow::Result<()> {
+    let path = record_path(adapter)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(record)?)?;
+    Ok(())
+}
+
+fn file_sha256(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    Some(hex::encode(Sha256::digest(bytes)))
+}
+
+/// Snapshots `path` the first time `adapter` is about to patch it. Later
+/// calls keep the first snapshot, but note when the user edited the file
+/// since pater's last write so unsync unpatches instead of restoring.
+pub fn capture_original(adapter: &str, path: &Path) -> anyhow::Result<()> {
+    if let Some(mut record) = load_record(adapter)?.filter(|r| r.path == path) {
+        if !record.edited_between_syncs
+            && record.written_sha256.is_some()
+            && file_sha256(path) != record.written_sha256
+        {
+            record.edited_between_syncs = true;
+            save_record(adapter, &record)?;
+        }
+        return Ok(());
+    }
+    let content = if path.exists() {
+        Some(std::fs::read_to_string(path)?)
+    } else {
+        None
+    };
+    let parent_existed = path.parent().is_none_or(|p| p.exists());
+    save_record(
+        adapter,
+        &OriginalConfig {
+            path: path.to_path_buf(),
+            content,
+            parent_existed,
+            written_sha256: None,
+            edited_between_syncs: false,
+        },
+    )
+}
+
+pub fn record_written(adapter: &str, path: &Path) -> anyhow::Result<()> {
+    if let Some(mut record) = load_record(adapter)? {
+        record.written_sha256 = file_sha256(path);
+        save_record(adapter, &record)?;
+    }
+    Ok(())
+}
+
+/// Puts the snapshot back when the file holds exactly what pater last wrote
+/// and was never edited between syncs. The snapshot is dropped either way
+/// (kept on dry runs).
+pub fn restore_original(adapter: &str, changes: &mut ChangeSet) -> anyhow::Result<RestoreOutcome> {
+    let Some(record) = load_record(adapter)? else {
+        return Ok(RestoreOutcome::NoSnapshot);
+    };
+    let modified = record.edited_between_syncs
+        || record.written_sha256.is_some() && file_sha256(&record.path) != record.written_sha256;
+    let outcome = if modified {
+        RestoreOutcome::ModifiedSince
+    } else {
+        match &record.content {
+            Some(content) => changes.write(&record.path, content)?,
+            None => {
+                changes.remove(&record.path)?;
+                if !record.parent_existed && !changes.dry_run {
+                    if let Some(parent) = record.path.parent() {
+                        // Only succeeds when pater left nothing else behind.
+                        let _ = std::fs::remove_dir(parent);
+                    }
+                }
+            }
+        }
+        RestoreOutcome::Restored
+    };
+    if !changes.dry_run {
+        std::fs::remove_file(record_path(adapter)?)?;
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,459 @@
+use super::adapter_names;
+use super::changes::ChangeSet;
+use crate::domain::constants::REGISTRY_SCHEMA_VERSION;
+use crate::domain::models::{InstalledPlugin, PolicyFile, SkippedPlugin, State};
+use crate::rack;
+use crate::services::marketplace::agent_policy_allows;
+use crate::services::migrations::{check_existing, REGISTRY_SCHEMA};
+use crate::services::storage::{
+    materialize_plugin, runtime_base_dir, runtime_plugin_dir, runtime_registry_path,
+};
+use crate::services::store::{link_view, store_entry_path};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// What adapters project into agent configs, built from installed plugins.
+/// `build_runtime_registry` returns the view over every plugin; adapters get
+/// `for_adapter`, which keeps only plugins that target them.
+#[derive(Clone)]
+pub struct RuntimeView {
+    pub plugins: Vec<ViewPlugin>,
+    pub plugin_dirs: Vec<String>,
+    pub registry_path: PathBuf,
+    pub mcp_servers: Vec<McpServer>,
+    pub hooks: Vec<PluginHook>,
+    pub subagents: Vec<PluginSubagent>,
+    pub skills: Vec<PluginSkill>,
+    /// Plugins per-agent policy left out, with the adapter and reason.
+    pub skipped_plugins: Vec<SkippedPlugin>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ViewPlugin {
+    /// Namespace used for this plugin's entries in agent configs.
+    pub key: String,
+    pub dir: String,
+    /// Adapters the plugin is synced to (supported agents and user pin).
+    pub adapters: Vec<String>,
+}
+
+impl RuntimeView {
+    pub fn for_adapter(&self, adapter: &str) -> RuntimeView {
+        let plugins = self
+            .plugins
+            .iter()
+            .filter(|p| p.adapters.iter().any(|a| a == adapter))
+            .cloned()
+            .collect::<Vec<_>>();
+        let keep = |key: &str| plugins.iter().any(|p| p.key == key);
+        RuntimeView {
+            plugin_dirs: plugins.iter().map(|p| p.dir.clone()).collect(),
+            registry_path: self.registry_path.clone(),
+            mcp_servers: self
+                .mcp_servers
+                .iter()
+                .filter(|m| keep(&m.plugin))
+                .cloned()
+                .collect(),
+            hooks: self
+                .hooks
+                .iter()
+                .filter(|h| keep(&h.plugin))
+                .cloned()
+                .collect(),
+            subagents: self
+                .subagents
+                .iter()
+                .filter(|s| keep(&s.plugin))
+                .cloned()
+                .collect(),
+            skills: self
+                .skills
+                .iter()
+                .filter(|s| keep(&s.plugin))
+                .cloned()
+                .collect(),
+            skipped_plugins: self
+                .skipped_plugins
+                .iter()
+                .filter(|s| s.adapter == adapter)
+                .cloned()
+                .collect(),
+            plugins,
+        }
+    }
+}
+
+/// A plugin MCP server with runtime paths filled in.
+#[derive(Debug, Clone)]
+pub struct McpServer {
+    /// Namespace of the owning plugin.
+    pub plugin: String,
+    /// `<plugin>__<name>`, so servers from different plugins never collide;
+    /// prefixed with `<marketplace>__` when two marketplaces ship the plugin.
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub cwd: Option<String>,
+}
+
+/// A plugin hook with `${CLAUDE_PLUGIN_ROOT}` expanded in `run`.
+#[derive(Debug, Clone)]
+pub struct PluginHook {
+    /// Namespace of the owning plugin, as used for MCP server names.
+    pub plugin: String,
+    pub agent: String,
+    /// Generic event, optionally `<event>:<matcher>`; see `hooks.rs`.
+    pub event: String,
+    pub run: String,
+}
+
+/// A skill dir inside a runtime plugin.
+#[derive(Debug, Clone)]
+pub struct PluginSkill {
+    /// Namespace of the owning plugin, as used for MCP server names.
+    pub plugin: String,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginSubagent {
+    /// Namespace of the owning plugin, as used for MCP server names.
+    pub plugin: String,
+    pub name: String,
+    pub purpose: String,
+    pub prompt: Option<String>,
+    pub tools: Vec<String>,
+    pub model: Option<String>,
+}
+
+fn expand_plugin_root(raw: &str, runtime_dir: &Path) -> String {
+    let root = runtime_dir.to_string_lossy();
+    raw.replace("${CLAUDE_PLUGIN_ROOT}", &root)
+        .replace("${PATER_PLUGIN_ROOT}", &root)
+}
+
+fn config_key_segment(raw: &str) -> String {
+    raw.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Namespace for a plugin's entries in agent configs: the plugin name, or
+/// `<marketplace>__<plugin>` when more than one marketplace ships that name.
+fn plugin_key(state: &State, installed: &InstalledPlugin) -> String {
+    let shared = state
+        .installed
+        .iter()
+        .filter(|p| p.name == installed.name)
+        .count()
+        > 1;
+    if shared {
+        format!(
+            "{}__{}",
+            config_key_segment(&installed.marketplace),
+            config_key_segment(&installed.name)
+        )
+    } else {
+        config_key_segment(&installed.name)
+    }
+}
+
+/// Builds a server from a manifest `mcps` entry. `${CLAUDE_PLUGIN_ROOT}` and
+/// `${PATER_PLUGIN_ROOT}` expand to the runtime dir; a relative `cwd` is
+/// taken relative to it. Entries without `name`/`command` are skipped.
+fn mcp_server(prefix: &str, runtime_dir: &Path, config: &serde_json::Value) -> Option<McpServer> {
+    let expand = |s: &str| expand_plugin_root(s, runtime_dir);
+    let name = config.get("name")?.as_str()?;
+    let command = config.get("command")?.as_str()?;
+    let args = config
+        .get("args")
+        .and_then(|a| a.as_array())
+        .map(|a| a.iter().filter_map(|x| x.as_str()).map(expand).collect())
+        .unwrap_or_default();
+    let env = config
+        .get("env")
+        .and_then(|e| e.as_object())
+        .map(|e| {
+            e.iter()
+                .filter_map(|(k, v)| Some((k.clone(), expand(v.as_str()?))))
+                .collect()
+        })
+        .unwrap_or_default();
+    let cwd = config.get("cwd").and_then(|c| c.as_str()).map(|c| {
+        let c = expand(c);
+        if Path::new(&c).is_absolute() {
+            c
+        } else {
+            runtime_dir.join(c).to_string_lossy().to_string()
+        }
+    });
+    Some(McpServer {
+        plugin: prefix.to_string(),
+        name: format!("{}__{}", prefix, config_key_segment(name)),
+        command: expand(command),
+        args,
+        env,
+        cwd,
+    })
+}
+
+/// Runtime dir for `installed`, materializing it if missing. Dry runs only
+/// report where it would be.
+fn ensure_runtime_materialized(installed: &InstalledPlugin, dry_run: bool) -> Option<PathBuf> {
+    let runtime_dir = runtime_plugin_dir(&installed.marketplace, &installed.name).ok()?;
+    if runtime_dir.exists() || dry_run {
+        return Some(runtime_dir);
+    }
+
+    if let Some(hash) = &installed.store_hash {
+        if store_entry_path(hash).is_ok_and(|p| p.exists()) && link_view(hash, &runtime_dir).is_ok()
+        {
+            return Some(runtime_dir);
+        }
+    }
+
+    let local_path = PathBuf::from(&installed.local_path);
+    if local_path.exists() && local_path.is_dir() {
+        if let Ok((dst, _)) =
+            materialize_plugin(&installed.marketplace, &installed.name, &local_path)
+        {
+            return Some(dst);
+        }
+    }
+
+    if let Ok(src) = rack::resolve_plugin_path(&installed.marketplace_source, &installed.source) {
+        if let Ok((dst, _)) = materialize_plugin(&installed.marketplace, &installed.name, &src) {
+            return Some(dst);
+        }
+    }
+
+    None
+}
+
+fn load_manifest_mcps(runtime_dir: &Path) -> Vec<serde_json::Value> {
+    let manifest = runtime_dir.join(".claude-plugin").join("plugin.json");
+    if !manifest.exists() {
+        return Vec::new();
+    }
+
+    let Ok(raw) = std::fs::read_to_string(manifest) else {
+        return Vec::new();
+    };
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(&raw) else {
+        return Vec::new();
+    };
+
+    value
+        .get("mcps")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub(super) fn build_runtime_registry(
+    state: &State,
+    policy: &PolicyFile,
+    changes: &mut ChangeSet,
+) -> anyhow::Result<RuntimeView> {
+    let base = runtime_base_dir()?;
+    let adapters = adapter_names()?;
+
+    let mut view_plugins = Vec::new();
+    let mut plugins = Vec::new();
+    let mut skills = Vec::new();
+    let mut hooks = Vec::new();
+    let mut subagents = Vec::new();
+    let mut mcps = Vec::new();
+    let mut mcp_servers = Vec::new();
+    let mut plugin_hooks_view = Vec::new();
+    let mut plugin_subagents_view = Vec::new();
+    let mut plugin_skills_view = Vec::new();
+    let mut markets_cache: HashMap<String, rack::Marketplace> = HashMap::new();
+    let mut skipped_plugins = Vec::new();
+
+    for installed in &state.installed {
+        let Some(runtime_dir) = ensure_runtime_materialized(installed, changes.dry_run) else {
+            continue;
+        };
+
+        let runtime_path = runtime_dir.to_string_lossy().to_string();
+
+        let market = if let Some(cached) = markets_cache.get(&installed.marketplace_source) {
+            Some(cached.clone())
+        } else if let Ok(loaded) = rack::load_marketplace(&installed.marketplace_source) {
+            markets_cache.insert(installed.marketplace_source.clone(), loaded.clone());
+            Some(loaded)
+        } else {
+            None
+        };
+
+        let market_plugin = market
+            .as_ref()
+            .and_then(|m| m.plugins.iter().find(|p| p.name == installed.name));
+
+        let plugin_skills = market_plugin.map(|p| p.skills.clone()).unwrap_or_default();
+
+        let plugin_hooks = market_plugin.map(|p| p.hooks.clone()).unwrap_or_default();
+
+        let plugin_subagents = market_plugin
+            .map(|p| p.subagents.clone())
+            .unwrap_or_default();
+
+        let plugin_mcps = load_manifest_mcps(&runtime_dir);
+        let key = plugin_key(state, installed);
+        let mut targets = Vec::new();
+        for adapter in adapters.iter().filter(|a| installed.targets_adapter(a)) {
+            match agent_policy_allows(policy, installed, market_plugin, &runtime_dir, adapter) {
+                Ok(()) => targets.push(adapter.clone()),
+                Err(e) => skipped_plugins.push(SkippedPlugin {
+                    adapter: adapter.clone(),
+                    plugin: key.clone(),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        view_plugins.push(ViewPlugin {
+            key: key.clone(),
+            dir: runtime_path.clone(),
+            adapters: targets.clone(),
+        });
+
+        plugins.push(serde_json::json!({
+            "name": installed.name,
+            "marketplace": installed.marketplace,
+            "marketplace_source": installed.marketplace_source,
+            "source": installed.source,
+            "runtime_path": runtime_path,
+            "permissions": installed.permissions,
+            "version": installed.version,
+            "scope": installed.scope,
+            "adapters": targets,
+        }));
+
+        for skill in &plugin_skills {
+            let path = runtime_dir.join("skills").join(skill);
+            skills.push(serde_json::json!({
+                "plugin": installed.name,
+                "name": skill,
+                "path": path.to_string_lossy(),
+            }));
+            if path.is_dir() {
+                plugin_skills_view.push(PluginSkill {
+                    plugin: key.clone(),
+                    name: skill.clone(),
+                    path,
+                });
+            }
+        }
+
+        for hook in plugin_hooks {
+            plugin_hooks_view.push(PluginHook {
+                plugin: key.clone(),
+                agent: hook.agent.clone(),
+                event: hook.event.clone(),
+                run: expand_plugin_root(&hook.run, &runtime_dir),
+            });
+            hooks.push(serde_json::json!({
+                "plugin": installed.name,
+                "agent": hook.agent,
+                "event": hook.event,
+                "run": hook.run,
+            }));
+        }
+
+        for subagent in plugin_subagents {
+            subagents.push(serde_json::json!({
+                "plugin": installed.name,
+                "name": subagent.name,
+                "purpose": subagent.purpose,
+                "prompt": subagent.prompt,
+                "tools": subagent.tools,
+                "model": subagent.model,
+            }));
+            plugin_subagents_view.push(PluginSubagent {
+                plugin: key.clone(),
+                name: subagent.name,
+                purpose: subagent.purpose,
+                prompt: subagent.prompt,
+                tools: subagent.tools,
+                model: subagent.model,
+            });
+        }
+
+        for mcp in plugin_mcps {
+            mcp_servers.extend(mcp_server(&key, &runtime_dir, &mcp));
+            mcps.push(serde_json::json!({
+                "plugin": installed.name,
+                "config": mcp,
+            }));
+        }
+    }
+
+    view_plugins.sort_by(|a, b| a.dir.cmp(&b.dir));
+    plugins.sort_by(|a, b| {
+        let an = a.get("name").and_then(|x| x.as_str()).unwrap_or("");
+        let bn = b.get("name").and_then(|x| x.as_str()).unwrap_or("");
+        an.cmp(bn)
+    });
+
+    let registry = serde_json::json!({
+        "managedBy": "pater",
+        "schemaVersion": REGISTRY_SCHEMA_VERSION,
+        "runtime_base": base,
+        "plugins": plugins,
+        "skills": skills,
+        "hooks": hooks,
+        "subagents": subagents,
+        "mcps": mcps,
+    });
+
+    let registry_path = runtime_registry_path()?;
+    check_existing(&registry_path, &REGISTRY_SCHEMA)?;
+    changes.write(&registry_path, &serde_json::to_string_pretty(&registry)?)?;
+
+    mcp_servers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(RuntimeView {
+        plugin_dirs: view_plugins.iter().map(|p| p.dir.clone()).collect(),
+        plugins: view_plugins,
+        registry_path,
+        mcp_servers,
+        hooks: plugin_hooks_view,
+        subagents: plugin_subagents_view,
+        skills: plugin_skills_view,
+        skipped_plugins,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mcp_server;
+    use std::path::Path;
+
+    #[test]
+    fn mcp_server_expands_plugin_root_and_relative_cwd() {
+        let root = Path::new("/rt/plugins/m/p");
+        let cfg = serde_json::json!({
+            "name": "git hub",
+            "command": "${CLAUDE_PLUGIN_ROOT}/bin/mcp",
+            "args": ["--data", "${PATER_PLUGIN_ROOT}/data"],
+            "env": {"MODE": "ro"},
+            "cwd": "work",
+        });
+        let server = mcp_server("p", root, &cfg).expect("server");
+        assert_eq!(server.name, "p__git-hub");
+        assert_eq!(server.command, "/rt/plugins/m/p/bin/mcp");
+        assert_eq!(server.args, ["--data", "/rt/plugins/m/p/data"]);
+        assert_eq!(server.env["MODE"], "ro");
+        assert_eq!(server.cwd.as_deref(), Some("/rt/plugins/m/p/work"));
+        assert!(mcp_server("p", root, &serde_json::json!({"name": "x"})).is_none());
+    }
+}
@@ -0,0 +1,73 @@
+//! Links plugin skills into an agent's native skills dir as
+//! `<plugin>-<skill>`. Pater only touches entries it manages: symlinks into
+//! the runtime dir, or copies holding the store's `MANAGED_MARKER` where symlinks are
+//! unavailable.
+
+use super::agents::{check_native_names, native_name};
+use super::changes::{copy_marker, ChangeSet};
+use super::PluginSkill;
+use crate::services::storage::runtime_base_dir;
+use std::path::{Path, PathBuf};
+
+fn is_managed(entry: &Path) -> bool {
+    if let Ok(target) = std::fs::read_link(entry) {
+        return runtime_base_dir().is_ok_and(|base| target.starts_with(base));
+    }
+    copy_marker(entry).is_some()
+}
+
+fn managed_entries(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut out = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| is_managed(p))
+        .collect::<Vec<_>>();
+    out.sort();
+    Ok(out)
+}
+
+/// Links every skill into `dir` and removes managed entries whose skill is
+/// gone. Refuses to replace a same-named entry pater did not create.
+pub fn link_skills(
+    dir: &Path,
+    skills: &[PluginSkill],
+    changes: &mut ChangeSet,
+) -> anyhow::Result<()> {
+    check_native_names(skills.iter().map(|s| (s.plugin.as_str(), s.name.as_str())))?;
+    let wanted = skills
+        .iter()
+        .map(|s| (dir.join(native_name(&s.plugin, &s.name)), s.path.as_path()))
+        .collect::<Vec<_>>();
+    if let Some((taken, _)) = wanted
+        .iter()
+        .find(|(p, _)| std::fs::symlink_metadata(p).is_ok() && !is_managed(p))
+    {
+        anyhow::bail!(
+            "refusing to replace {}: entry exists and is not managed by pater",
+            taken.display()
+        );
+    }
+    for (path, target) in &wanted {
+        changes.link_dir(path, target)?;
+    }
+    for stale in managed_entries(dir)? {
+        if !wanted.iter().any(|(p, _)| *p == stale) {
+            changes.remove_tree(&stale)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes every managed entry from `dir`, then `dir` if that left it empty.
+pub fn unlink_skills(dir: &Path, changes: &mut ChangeSet) -> anyhow::Result<()> {
+    for entry in managed_entries(dir)? {
+        changes.remove_tree(&entry)?;
+    }
+    if !changes.dry_run {
+        // Only succeeds when nothing else lives there.
+        let _ = std::fs::remove_dir(dir);
+    }
+    Ok(())
+}
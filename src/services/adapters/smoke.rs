@@ -0,0 +1,314 @@
+//! `adapter smoke`: reads back what sync wrote and checks it against the
+//! runtime registry. Covers the shim, the bridge, pater's entry in the agent
+//! config, the wrapper's paths and the MCP commands registered natively.
+
+use super::wrapper::{wrapper_path, ScriptKind};
+use super::{bridge_file_path, resolve_adapters, Adapter};
+use crate::domain::models::{SmokeCheck, SmokeReport, State};
+use crate::services::storage::{home_dir, runtime_plugin_dir, runtime_registry_path};
+use std::path::{Path, PathBuf};
+
+fn passed(name: &str) -> SmokeCheck {
+    SmokeCheck {
+        name: name.to_string(),
+        status: "ok".to_string(),
+        detail: None,
+    }
+}
+
+fn failed(name: &str, status: &str, detail: impl Into<String>) -> SmokeCheck {
+    SmokeCheck {
+        name: name.to_string(),
+        status: status.to_string(),
+        detail: Some(detail.into()),
+    }
+}
+
+/// `plugin_dirs` from a pater-written JSON index (shim, bridge).
+pub(super) fn json_plugin_dirs(path: &Path) -> Option<Vec<String>> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let v = serde_json::from_str::<serde_json::Value>(&raw).ok()?;
+    let dirs = v.get("plugin_dirs")?.as_array()?;
+    Some(
+        dirs.iter()
+            .filter_map(|d| d.as_str().map(str::to_string))
+            .collect(),
+    )
+}
+
+/// Runtime dirs `registry.json` assigns to `adapter`. Entries written before
+/// per-plugin targeting have no `adapters` and count for every adapter.
+fn registry_plugin_dirs(registry: &serde_json::Value, adapter: &str) -> Vec<String> {
+    let mut dirs = registry
+        .get("plugins")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|p| {
+            p.get("adapters")
+                .and_then(|a| a.as_array())
+                .is_none_or(|a| a.iter().any(|x| x == adapter))
+        })
+        .filter_map(|p| p.get("runtime_path").and_then(|r| r.as_str()))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+fn load_registry() -> Result<serde_json::Value, SmokeCheck> {
+    let path = runtime_registry_path()
+        .map_err(|e| failed("registry", "missing_registry", e.to_string()))?;
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|_| failed("registry", "missing_registry", path.display().to_string()))?;
+    serde_json::from_str(&raw).map_err(|e| failed("registry", "unreadable_registry", e.to_string()))
+}
+
+/// Compares the plugin dirs a synced file references with the registry's.
+/// Without a registry there is nothing to compare; its own check fails.
+fn check_plugin_dirs(
+    name: &str,
+    found: Option<Vec<String>>,
+    expected: Option<&[String]>,
+    missing_status: &str,
+    missing_detail: &Path,
+) -> SmokeCheck {
+    let Some(mut found) = found else {
+        return failed(name, missing_status, missing_detail.display().to_string());
+    };
+    let Some(expected) = expected else {
+        return passed(name);
+    };
+    found.sort();
+    if found == expected {
+        return passed(name);
+    }
+    let absent = expected.iter().filter(|d| !found.contains(d));
+    let extra = found.iter().filter(|d| !expected.contains(d));
+    let detail = absent
+        .map(|d| format!("missing {}", d))
+        .chain(extra.map(|d| format!("unexpected {}", d)))
+        .collect::<Vec<_>>()
+        .join("; ");
+    failed(name, "stale_plugin_dirs", detail)
+}
+
+fn check_wrapper(adapter: &dyn Adapter, home: &Path) -> Option<SmokeCheck> {
+    let spec = adapter.wrapper()?;
+    let path = wrapper_path(home, &spec);
+    let Ok(script) = std::fs::read_to_string(&path) else {
+        return Some(failed(
+            "wrapper",
+            "missing_wrapper",
+            path.display().to_string(),
+        ));
+    };
+    let broken = ScriptKind::native()[0]
+        .quoted_args(&script)
+        .into_iter()
+        .filter(|a| Path::new(a).is_absolute() && !Path::new(a).exists())
+        .collect::<Vec<_>>();
+    Some(if broken.is_empty() {
+        passed("wrapper")
+    } else {
+        failed("wrapper", "broken_wrapper", broken.join(", "))
+    })
+}
+
+/// File names `command` may resolve to. With `pathext` (Windows), a command
+/// without an extension is also tried with each `PATHEXT` extension, since
+/// `npx` or `uvx` exist only as `.cmd`/`.exe` there.
+fn command_names(command: &str, pathext: Option<&str>) -> Vec<String> {
+    let mut names = vec![command.to_string()];
+    if let Some(pathext) = pathext {
+        if Path::new(command).extension().is_none() {
+            names.extend(
+                pathext
+                    .split(';')
+                    .filter(|e| !e.is_empty())
+                    .map(|e| format!("{}{}", command, e)),
+            );
+        }
+    }
+    names
+}
+
+/// Whether `command` names an existing file, directly or via `PATH`.
+fn command_resolves(command: &str) -> bool {
+    if command.is_empty() {
+        return false;
+    }
+    let pathext = cfg!(windows)
+        .then(|| std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string()));
+    let names = command_names(command, pathext.as_deref());
+    if command.contains('/') || command.contains(std::path::MAIN_SEPARATOR) {
+        return names.iter().any(|n| Path::new(n).is_file());
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|d| names.iter().any(|n| d.join(n).is_file()))
+    })
+}
+
+fn check_mcp(name: &str, command: &str) -> SmokeCheck {
+    let check = format!("mcp:{}", name);
+    if command_resolves(command) {
+        passed(&check)
+    } else {
+        failed(&check, "mcp_command_not_found", command)
+    }
+}
+
+fn smoke_adapter(
+    adapter: &dyn Adapter,
+    home: Option<&PathBuf>,
+    registry: &Result<serde_json::Value, SmokeCheck>,
+    missing_plugins: &[String],
+) -> anyhow::Result<Vec<SmokeCheck>> {
+    let expected = registry
+        .as_ref()
+        .ok()
+        .map(|r| registry_plugin_dirs(r, adapter.name()));
+    let expected = expected.as_deref();
+    let mut checks = Vec::new();
+
+    let Some(home) = home else {
+        checks.push(failed("shim", "missing_shim", "home directory unknown"));
+        return Ok(checks);
+    };
+    let shim = adapter.shim_path(home);
+    checks.push(check_plugin_dirs(
+        "shim",
+        json_plugin_dirs(&shim),
+        expected,
+        "missing_shim",
+        &shim,
+    ));
+    let bridge = bridge_file_path(adapter.name())?;
+    checks.push(check_plugin_dirs(
+        "bridge",
+        json_plugin_dirs(&bridge),
+        expected,
+        "missing_bridge",
+        &bridge,
+    ));
+
+    checks.push(if missing_plugins.is_empty() {
+        passed("runtime_plugins")
+    } else {
+        failed(
+            "runtime_plugins",
+            "missing_runtime_plugins",
+            missing_plugins.join(", "),
+        )
+    });
+    checks.push(match registry {
+        Ok(_) => passed("registry"),
+        Err(check) => failed(
+            &check.name,
+            &check.status,
+            check.detail.clone().unwrap_or_default(),
+        ),
+    });
+
+    // OpenClaw-style adapters read the shim itself; it was checked above.
+    let (config_name, config) = adapter.config_file(home);
+    if config != shim {
+        checks.push(check_plugin_dirs(
+            &config_name,
+            adapter.configured_plugin_dirs(home),
+            expected,
+            "missing_config_patch",
+            &config,
+        ));
+    }
+    checks.extend(check_wrapper(adapter, home));
+    for (name, command) in adapter.configured_mcp_commands(home) {
+        checks.push(check_mcp(&name, &command));
+    }
+    Ok(checks)
+}
+
+pub fn adapter_smoke(state: &State, target: &str) -> anyhow::Result<Vec<SmokeReport>> {
+    let home = home_dir().ok();
+    let registry = load_registry();
+    let mut out = Vec::new();
+
+    for adapter in resolve_adapters(target)? {
+        let targeted = state
+            .installed
+            .iter()
+            .filter(|p| p.targets_adapter(adapter.name()))
+            .collect::<Vec<_>>();
+        let mut missing = Vec::new();
+        for p in &targeted {
+            if !runtime_plugin_dir(&p.marketplace, &p.name)?.exists() {
+                missing.push(p.name.clone());
+            }
+        }
+
+        let checks = smoke_adapter(adapter.as_ref(), home.as_ref(), &registry, &missing)?;
+        let status = checks
+            .iter()
+            .find(|c| c.status != "ok")
+            .map_or("ok", |c| c.status.as_str())
+            .to_string();
+
+        out.push(SmokeReport {
+            adapter: adapter.name().to_string(),
+            status,
+            checked_plugins: targeted.len(),
+            missing_plugins: missing,
+            checks,
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_dirs_follow_adapter_targets() {
+        let registry = serde_json::json!({"plugins": [
+            {"runtime_path": "/rt/b", "adapters": ["claude"]},
+            {"runtime_path": "/rt/a"},
+            {"runtime_path": "/rt/c", "adapters": ["codex"]},
+        ]});
+        assert_eq!(
+            registry_plugin_dirs(&registry, "claude"),
+            ["/rt/a", "/rt/b"]
+        );
+        assert_eq!(registry_plugin_dirs(&registry, "codex"), ["/rt/a", "/rt/c"]);
+    }
+
+    #[test]
+    fn plugin_dir_mismatches_are_named() {
+        let expected = vec!["/rt/a".to_string(), "/rt/b".to_string()];
+        let found = Some(vec!["/rt/b".to_string(), "/rt/a".to_string()]);
+        let cfg = Path::new("/cfg");
+        let ok = check_plugin_dirs("cfg", found, Some(&expected), "missing", cfg);
+        assert_eq!(ok.status, "ok");
+
+        let found = Some(vec!["/rt/a".to_string(), "/rt/old".to_string()]);
+        let stale = check_plugin_dirs("cfg", found, Some(&expected), "missing", cfg);
+        assert_eq!(stale.status, "stale_plugin_dirs");
+        assert_eq!(
+            stale.detail.as_deref(),
+            Some("missing /rt/b; unexpected /rt/old")
+        );
+
+        let missing = check_plugin_dirs("cfg", None, Some(&expected), "missing", cfg);
+        assert_eq!(missing.status, "missing");
+    }
+
+    #[test]
+    fn commands_without_extension_try_pathext() {
+        assert_eq!(
+            command_names("npx", Some(".EXE;.CMD;")),
+            ["npx", "npx.EXE", "npx.CMD"]
+        );
+        assert_eq!(command_names("node.exe", Some(".EXE;.CMD")), ["node.exe"]);
+        assert_eq!(command_names("npx", None), ["npx"]);
+    }
+}
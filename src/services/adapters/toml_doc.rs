@@ -0,0 +1,138 @@
+//! Format-preserving TOML edits. Comments, key order and whitespace outside
+//! the touched key survive; every result is re-parsed before it is written.
+
+use super::changes::unparseable;
+use std::path::Path;
+use toml_edit::{Array, DocumentMut, Item, Table, TableLike};
+
+pub fn parse_document(path: &Path, raw: &str) -> anyhow::Result<DocumentMut> {
+    raw.parse::<DocumentMut>().map_err(|e| unparseable(path, e))
+}
+
+pub fn string_array(items: &[String]) -> Item {
+    let mut arr = Array::new();
+    for item in items {
+        arr.push(item.as_str());
+    }
+    toml_edit::value(arr)
+}
+
+/// String items of an array value; `None` if `item` is not an array.
+pub fn string_values(item: &Item) -> Option<Vec<String>> {
+    item.as_array().map(|a| {
+        a.iter()
+            .filter_map(|x| x.as_str().map(str::to_string))
+            .collect()
+    })
+}
+
+/// Sets dotted `key` to `item`, reusing existing (inline or standard) tables
+/// on the way and creating implicit ones where missing.
+pub fn set_dotted(doc: &mut DocumentMut, key: &str, item: Item) -> anyhow::Result<()> {
+    let parts = key.split('.').collect::<Vec<_>>();
+    let (leaf, parents) = parts.split_last().expect("split yields at least one part");
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parents {
+        if table.get(part).is_none() {
+            let mut fresh = Table::new();
+            fresh.set_implicit(true);
+            table.insert(part, Item::Table(fresh));
+        }
+        table = table
+            .get_mut(part)
+            .and_then(|i| i.as_table_like_mut())
+            .ok_or_else(|| anyhow::anyhow!("cannot set {}: {} is not a table", key, part))?;
+    }
+    table.insert(leaf, item);
+    Ok(())
+}
+
+pub fn get_dotted<'a>(doc: &'a DocumentMut, key: &str) -> Option<&'a Item> {
+    let mut item = doc.as_item();
+    for part in key.split('.') {
+        item = item.as_table_like()?.get(part)?;
+    }
+    Some(item)
+}
+
+/// Removes dotted `key`, then any parent tables it leaves empty.
+pub fn remove_dotted(doc: &mut DocumentMut, key: &str) -> bool {
+    fn remove_in(table: &mut dyn TableLike, parts: &[&str]) -> bool {
+        match parts {
+            [] => false,
+            [leaf] => table.remove(leaf).is_some(),
+            [head, rest @ ..] => {
+                let Some(child) = table.get_mut(head).and_then(|i| i.as_table_like_mut()) else {
+                    return false;
+                };
+                let removed = remove_in(child, rest);
+                if removed && child.is_empty() {
+                    table.remove(head);
+                }
+                removed
+            }
+        }
+    }
+    let parts = key.split('.').collect::<Vec<_>>();
+    remove_in(doc.as_table_mut(), &parts)
+}
+
+/// Serializes `doc` and checks the output still parses.
+pub fn render_checked(path: &Path, doc: &DocumentMut) -> anyhow::Result<String> {
+    let out = doc.to_string();
+    toml::from_str::<toml::Table>(&out).map_err(|e| {
+        anyhow::anyhow!(
+            "refusing to write {}: edited TOML does not parse: {}",
+            path.display(),
+            e
+        )
+    })?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(raw: &str, key: &str) -> String {
+        let path = Path::new("config.toml");
+        let mut doc = parse_document(path, raw).expect("parse");
+        set_dotted(&mut doc, key, string_array(&["/rt/a".to_string()])).expect("set");
+        render_checked(path, &doc).expect("render")
+    }
+
+    #[test]
+    fn set_preserves_comments_and_existing_tables() {
+        let raw = "# top comment\nmodel = \"o3\" # inline\n\n[pater]\nprofile = \"work\"\n\n[other]\nx = 1\n";
+        let out = edit(raw, "pater.plugin_dirs");
+        assert!(out.starts_with("# top comment\nmodel = \"o3\" # inline\n"));
+        assert!(out.contains("[pater]\nprofile = \"work\"\nplugin_dirs = [\"/rt/a\"]\n"));
+        assert!(out.contains("[other]\nx = 1\n"));
+    }
+
+    #[test]
+    fn set_reaches_into_inline_and_nested_tables() {
+        let out = edit("pater = { profile = \"w\" }\n", "pater.plugin_dirs");
+        assert!(out.starts_with("pater = { profile = \"w\""));
+        assert!(out.contains("plugin_dirs = [\"/rt/a\"] }"));
+        assert!(!out.contains("[pater]"));
+
+        let out = edit("", "tools.pater.plugin_dirs");
+        assert!(out.contains("[tools.pater]\nplugin_dirs = [\"/rt/a\"]\n"));
+        assert!(!out.contains("[tools]\n"));
+    }
+
+    #[test]
+    fn remove_prunes_only_emptied_tables() {
+        let path = Path::new("config.toml");
+        let raw =
+            "model = \"o3\" # keep me\n\n[pater]\nplugin_dirs = [\"/rt/a\"]\n\n[other]\nx = 1\n";
+        let mut doc = parse_document(path, raw).expect("parse");
+        assert!(remove_dotted(&mut doc, "pater.plugin_dirs"));
+        let out = render_checked(path, &doc).expect("render");
+        assert!(!out.contains("[pater]"));
+        assert!(out.contains("# keep me"));
+        assert!(out.contains("[other]\nx = 1\n"));
+        assert!(!remove_dotted(&mut doc, "pater.plugin_dirs"));
+    }
+}
@@ -0,0 +1,224 @@
+use super::changes::ChangeSet;
+use std::path::{Path, PathBuf};
+
+/// Launcher script pater installs into `~/.local/bin` for an agent CLI.
+pub struct WrapperSpec {
+    /// Script file name without extension, e.g. `pater-claude`.
+    pub name: String,
+    /// Command the script execs; `{plugin_args}` marks where plugin-dir
+    /// flags go, otherwise they follow the command.
+    pub command: String,
+    /// Flag passed once per runtime plugin dir; `{dir}` marks where the path
+    /// goes, otherwise it is a separate argument.
+    pub plugin_dir_flag: String,
+}
+
+/// Script flavours a wrapper is written in. Unix gets one `sh` script;
+/// Windows gets a `.cmd` for cmd.exe and a `.ps1` for PowerShell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    Sh,
+    Cmd,
+    PowerShell,
+}
+
+impl ScriptKind {
+    /// Flavours written on this platform; the first is the one checked by
+    /// doctor and smoke.
+    pub fn native() -> &'static [ScriptKind] {
+        if cfg!(windows) {
+            &[ScriptKind::Cmd, ScriptKind::PowerShell]
+        } else {
+            &[ScriptKind::Sh]
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ScriptKind::Sh => "",
+            ScriptKind::Cmd => ".cmd",
+            ScriptKind::PowerShell => ".ps1",
+        }
+    }
+
+    fn quote(self, raw: &str) -> String {
+        match self {
+            ScriptKind::Sh => format!("'{}'", raw.replace('\'', "'\\''")),
+            // Windows paths cannot contain `"`; `%` would expand as a variable.
+            ScriptKind::Cmd => format!("\"{}\"", raw.replace('%', "%%")),
+            ScriptKind::PowerShell => format!("'{}'", raw.replace('\'', "''")),
+        }
+    }
+
+    fn script(self, command_line: &str) -> String {
+        match self {
+            ScriptKind::Sh => format!("#!/usr/bin/env sh\nexec {} \"$@\"\n", command_line),
+            ScriptKind::Cmd => format!("@echo off\r\n{} %*\r\n", command_line),
+            ScriptKind::PowerShell => format!("& {} @args\r\nexit $LASTEXITCODE\r\n", command_line),
+        }
+    }
+
+    /// Quoted arguments in a script of this kind, unquoted; the
+    /// inverse of `quote`. Smoke uses it to find the paths a wrapper passes.
+    pub fn quoted_args(self, script: &str) -> Vec<String> {
+        let quote = if self == ScriptKind::Cmd { '"' } else { '\'' };
+        // How a literal `'` continues an argument right after a closing quote.
+        let requote = match self {
+            ScriptKind::Sh => Some("\\''"),
+            ScriptKind::PowerShell => Some("'"),
+            ScriptKind::Cmd => None,
+        };
+        let mut out = Vec::new();
+        let mut rest = script;
+        while let Some(start) = rest.find(quote) {
+            rest = &rest[start + 1..];
+            let mut arg = String::new();
+            loop {
+                let Some(end) = rest.find(quote) else {
+                    return out;
+                };
+                arg.push_str(&rest[..end]);
+                rest = &rest[end + 1..];
+                match requote.and_then(|r| rest.strip_prefix(r)) {
+                    Some(more) => {
+                        arg.push('\'');
+                        rest = more;
+                    }
+                    None => break,
+                }
+            }
+            out.push(if self == ScriptKind::Cmd {
+                arg.replace("%%", "%")
+            } else {
+                arg
+            });
+        }
+        out
+    }
+}
+
+fn render_command(spec: &WrapperSpec, plugin_dirs: &[String], kind: ScriptKind) -> String {
+    let args = plugin_dirs
+        .iter()
+        .map(|d| {
+            if spec.plugin_dir_flag.contains("{dir}") {
+                spec.plugin_dir_flag.replace("{dir}", &kind.quote(d))
+            } else {
+                format!("{} {}", spec.plugin_dir_flag, kind.quote(d))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if spec.command.contains("{plugin_args}") {
+        spec.command.replace("{plugin_args}", &args)
+    } else if args.is_empty() {
+        spec.command.clone()
+    } else {
+        format!("{} {}", spec.command, args)
+    }
+}
+
+pub fn wrapper_bin_dir(home: &Path) -> PathBuf {
+    home.join(".local/bin")
+}
+
+pub fn wrapper_path_for(home: &Path, spec: &WrapperSpec, kind: ScriptKind) -> PathBuf {
+    wrapper_bin_dir(home).join(format!("{}{}", spec.name, kind.extension()))
+}
+
+/// The platform's primary wrapper script (see `ScriptKind::native`).
+pub fn wrapper_path(home: &Path, spec: &WrapperSpec) -> PathBuf {
+    wrapper_path_for(home, spec, ScriptKind::native()[0])
+}
+
+pub fn write_wrapper(
+    home: &Path,
+    spec: &WrapperSpec,
+    plugin_dirs: &[String],
+    changes: &mut ChangeSet,
+) -> anyhow::Result<()> {
+    for &kind in ScriptKind::native() {
+        let script = kind.script(&render_command(spec, plugin_dirs, kind));
+        changes.write_script(&wrapper_path_for(home, spec, kind), &script)?;
+    }
+    Ok(())
+}
+
+pub fn remove_wrapper(
+    home: &Path,
+    spec: &WrapperSpec,
+    changes: &mut ChangeSet,
+) -> anyhow::Result<()> {
+    for &kind in ScriptKind::native() {
+        changes.remove(&wrapper_path_for(home, spec, kind))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(command: &str, flag: &str) -> WrapperSpec {
+        WrapperSpec {
+            name: "pater-x".to_string(),
+            command: command.to_string(),
+            plugin_dir_flag: flag.to_string(),
+        }
+    }
+
+    #[test]
+    fn flags_follow_command_or_fill_templates() {
+        let dirs = vec!["/a".to_string(), "/it's".to_string()];
+        assert_eq!(
+            render_command(&spec("claude", "--plugin-dir"), &dirs, ScriptKind::Sh),
+            "claude --plugin-dir '/a' --plugin-dir '/it'\\''s'"
+        );
+        assert_eq!(
+            render_command(
+                &spec("h --profile w {plugin_args} --", "--ext={dir}"),
+                &dirs[..1],
+                ScriptKind::Sh
+            ),
+            "h --profile w --ext='/a' --"
+        );
+        assert_eq!(
+            render_command(&spec("codex", "--plugin-dir"), &[], ScriptKind::Sh),
+            "codex"
+        );
+    }
+
+    #[test]
+    fn windows_scripts_quote_for_their_shell() {
+        let dirs = vec![r"C:\rt\it's 100%".to_string()];
+        let spec = spec("claude", "--plugin-dir");
+        assert_eq!(
+            ScriptKind::Cmd.script(&render_command(&spec, &dirs, ScriptKind::Cmd)),
+            "@echo off\r\nclaude --plugin-dir \"C:\\rt\\it's 100%%\" %*\r\n"
+        );
+        assert_eq!(
+            ScriptKind::PowerShell.script(&render_command(&spec, &dirs, ScriptKind::PowerShell)),
+            "& claude --plugin-dir 'C:\\rt\\it''s 100%' @args\r\nexit $LASTEXITCODE\r\n"
+        );
+        let home = Path::new("/home/u");
+        assert_eq!(
+            wrapper_path_for(home, &spec, ScriptKind::Cmd),
+            Path::new("/home/u/.local/bin/pater-x.cmd")
+        );
+        assert_eq!(
+            wrapper_path_for(home, &spec, ScriptKind::PowerShell),
+            Path::new("/home/u/.local/bin/pater-x.ps1")
+        );
+    }
+
+    #[test]
+    fn quoted_args_round_trip_rendered_dirs() {
+        let dirs = vec!["/a b".to_string(), "/it's 100%".to_string()];
+        let spec = spec("h {plugin_args} --", "--ext={dir}");
+        for kind in [ScriptKind::Sh, ScriptKind::Cmd, ScriptKind::PowerShell] {
+            let script = kind.script(&render_command(&spec, &dirs, kind));
+            assert_eq!(kind.quoted_args(&script), dirs, "{:?}", kind);
+        }
+        assert!(ScriptKind::Sh.quoted_args("exec claude \"$@\"").is_empty());
+    }
+}
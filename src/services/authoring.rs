@@ -1,3 +1,4 @@
+use crate::rack::Subagent;
 use std::path::PathBuf;
 
 fn rack_marketplace_path(rack_dir: &str) -> PathBuf {
@@ -155,17 +156,12 @@ pub fn skill_remove(rack_dir: &str, plugin: &str, name: &str) -> anyhow::Result<
     Ok(())
 }
 
-pub fn subagent_create(
-    rack_dir: &str,
-    plugin: &str,
-    name: &str,
-    purpose: &str,
-) -> anyhow::Result<()> {
+pub fn subagent_create(rack_dir: &str, plugin: &str, subagent: &Subagent) -> anyhow::Result<()> {
     let mut m = load_marketplace_value(rack_dir)?;
     let p = get_plugin_mut(&mut m, plugin)?;
     let arr = ensure_array_field(p, "subagents")?;
-    arr.retain(|x| x.get("name").and_then(|v| v.as_str()) != Some(name));
-    arr.push(serde_json::json!({"name":name,"purpose":purpose}));
+    arr.retain(|x| x.get("name").and_then(|v| v.as_str()) != Some(subagent.name.as_str()));
+    arr.push(serde_json::to_value(subagent)?);
     save_marketplace_value(rack_dir, &m)
 }
 
@@ -0,0 +1,185 @@
+use crate::cli::DEFAULT_MARKETPLACE_SOURCE;
+use crate::domain::models::{GcItem, GcReport, InstalledPlugin, State};
+use crate::rack;
+use crate::services::storage::{
+    runtime_marketplace_dir, runtime_plugin_dir, runtime_plugins_dir, store_base_dir,
+};
+use crate::services::store::{store_entry_path, view_store_hash};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+fn path_size(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| path_size(&e.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn referenced_runtime_market_dirs(state: &State) -> anyhow::Result<HashSet<PathBuf>> {
+    state
+        .installed
+        .iter()
+        .map(|p| runtime_marketplace_dir(&p.marketplace))
+        .collect()
+}
+
+fn referenced_runtime_dirs(state: &State) -> anyhow::Result<HashSet<PathBuf>> {
+    state
+        .installed
+        .iter()
+        .map(|p| runtime_plugin_dir(&p.marketplace, &p.name))
+        .collect()
+}
+
+fn referenced_store_entries(state: &State) -> anyhow::Result<HashSet<PathBuf>> {
+    let mut hashes: HashSet<String> = state
+        .installed
+        .iter()
+        .filter_map(|p| p.store_hash.clone())
+        .collect();
+    for dir in referenced_runtime_dirs(state)? {
+        hashes.extend(view_store_hash(&dir));
+    }
+    hashes.iter().map(|h| store_entry_path(h)).collect()
+}
+
+fn referenced_repo_dirs(state: &State) -> anyhow::Result<HashSet<PathBuf>> {
+    let mut out = HashSet::new();
+    for p in &state.installed {
+        if let Some(source) = rack::plugin_repo_source(&p.marketplace_source, &p.source) {
+            out.insert(rack::repo_cache_path(&source)?);
+        }
+    }
+    Ok(out)
+}
+
+fn referenced_marketplace_caches(state: &State) -> anyhow::Result<HashSet<PathBuf>> {
+    let mut sources: Vec<&str> = vec![DEFAULT_MARKETPLACE_SOURCE];
+    sources.extend(state.marketplaces.iter().map(|m| m.source.as_str()));
+    sources.extend(
+        state
+            .installed
+            .iter()
+            .map(|p| p.marketplace_source.as_str()),
+    );
+    sources.into_iter().map(rack::cache_path).collect()
+}
+
+fn unreferenced_entries(
+    dir: &Path,
+    keep: &HashSet<PathBuf>,
+    kind: &str,
+) -> anyhow::Result<Vec<(GcItem, PathBuf)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if keep.contains(&path) {
+            continue;
+        }
+        out.push((
+            GcItem {
+                kind: kind.to_string(),
+                path: path.to_string_lossy().to_string(),
+                bytes: path_size(&path),
+            },
+            path,
+        ));
+    }
+    Ok(out)
+}
+
+fn remove_path(path: &Path) -> anyhow::Result<()> {
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Deletes what one removed plugin leaves behind: its runtime dir, its
+/// marketplace dir once empty, and store entries no remaining plugin
+/// references. Repo clones and caches are left to `pater gc`.
+pub fn prune_removed(state: &State, removed: &InstalledPlugin) -> anyhow::Result<()> {
+    let dir = runtime_plugin_dir(&removed.marketplace, &removed.name)?;
+    let mut hashes: Vec<String> = removed.store_hash.iter().cloned().collect();
+    hashes.extend(view_store_hash(&dir));
+    if std::fs::symlink_metadata(&dir).is_ok() {
+        remove_path(&dir)?;
+    }
+    let market_dir = runtime_marketplace_dir(&removed.marketplace)?;
+    if std::fs::read_dir(&market_dir).is_ok_and(|mut d| d.next().is_none()) {
+        std::fs::remove_dir(&market_dir)?;
+    }
+    let keep = referenced_store_entries(state)?;
+    for hash in hashes {
+        let entry = store_entry_path(&hash)?;
+        if !keep.contains(&entry) && std::fs::symlink_metadata(&entry).is_ok() {
+            remove_path(&entry)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes runtime plugin dirs, store entries, repo clones and marketplace
+/// caches that no installed plugin or configured marketplace references.
+pub fn collect_garbage(state: &State, dry_run: bool) -> anyhow::Result<GcReport> {
+    let cache = rack::cache_root()?;
+    // Runtime plugins live at `plugins/<marketplace>/<name>`; anything else at
+    // the top level (including pre-namespacing `plugins/<name>` dirs) is stale.
+    let market_dirs = referenced_runtime_market_dirs(state)?;
+    let plugin_dirs = referenced_runtime_dirs(state)?;
+    let mut candidates =
+        unreferenced_entries(&runtime_plugins_dir()?, &market_dirs, "runtime_plugin")?;
+    for market_dir in &market_dirs {
+        candidates.extend(unreferenced_entries(
+            market_dir,
+            &plugin_dirs,
+            "runtime_plugin",
+        )?);
+    }
+    candidates.extend(unreferenced_entries(
+        &store_base_dir()?,
+        &referenced_store_entries(state)?,
+        "store_entry",
+    )?);
+    candidates.extend(unreferenced_entries(
+        &cache.join("repos"),
+        &referenced_repo_dirs(state)?,
+        "repo_clone",
+    )?);
+    candidates.extend(unreferenced_entries(
+        &cache.join("marketplaces"),
+        &referenced_marketplace_caches(state)?,
+        "marketplace_cache",
+    )?);
+
+    let mut items = Vec::new();
+    for (item, path) in candidates {
+        if !dry_run {
+            remove_path(&path)?;
+        }
+        items.push(item);
+    }
+    items.sort_by(|a, b| a.kind.cmp(&b.kind).then(a.path.cmp(&b.path)));
+
+    Ok(GcReport {
+        dry_run,
+        reclaimed_bytes: items.iter().map(|i| i.bytes).sum(),
+        items,
+    })
+}
@@ -0,0 +1,58 @@
+/// Minimal glob matching: `*` and `?` stay within one `/`-separated segment,
+/// `**` crosses segments. No character classes.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    match_from(pattern.as_bytes(), text.as_bytes())
+}
+
+fn match_from(p: &[u8], t: &[u8]) -> bool {
+    if p.is_empty() {
+        return t.is_empty();
+    }
+    if p.starts_with(b"**") {
+        let mut rest = &p[2..];
+        if rest.first() == Some(&b'/') {
+            rest = &rest[1..];
+            // `**/x` also matches `x` at the current level.
+            if match_from(rest, t) {
+                return true;
+            }
+        }
+        return (0..=t.len()).any(|i| match_from(rest, &t[i..]));
+    }
+    match p[0] {
+        b'*' => {
+            let rest = &p[1..];
+            for i in 0..=t.len() {
+                if match_from(rest, &t[i..]) {
+                    return true;
+                }
+                if i < t.len() && t[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        b'?' => !t.is_empty() && t[0] != b'/' && match_from(&p[1..], &t[1..]),
+        c => !t.is_empty() && t[0] == c && match_from(&p[1..], &t[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn single_star_stays_in_segment() {
+        assert!(glob_match("experimental-*", "experimental-lsp"));
+        assert!(glob_match("*.log", "build.log"));
+        assert!(!glob_match("*.log", "logs/build.log"));
+        assert!(!glob_match("experimental-*", "stable"));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(glob_match("**/*.log", "logs/build.log"));
+        assert!(glob_match("**/*.log", "build.log"));
+        assert!(glob_match("docs/**", "docs/a/b.md"));
+    }
+}
@@ -1,15 +1,17 @@
-use crate::cli::{AdapterTarget, DEFAULT_MARKETPLACE_SOURCE};
+use crate::cli::{ALL_ADAPTERS, DEFAULT_MARKETPLACE_SOURCE};
 use crate::domain::models::{
-    DiscoverItem, MarketRef, PolicyEvalReport, PolicyFile, PolicyGeneral, Recommendation, State,
+    DiscoverItem, InstalledPlugin, MarketRef, PolicyAuditEntry, PolicyAuditReport,
+    PolicyEvalReport, PolicyFile, PolicyGeneral, PolicyRuleTrace, Recommendation, State,
     UpdateReport,
 };
 use crate::rack;
-use crate::services::policy::source_matches_allowed;
+use crate::services::permissions::{permission_delta, Permission};
+use crate::services::policy::{blocked_permissions, plugin_list_matches, source_matches_allowed};
 use crate::services::rack_ops::rack_license_audit_readonly;
 use crate::services::storage::{materialize_plugin, save_state};
 use crate::services::trust::verify_marketplace_signature;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn discover_item_from_plugin(
     marketplace_name: &str,
@@ -26,21 +28,10 @@ fn discover_item_from_plugin(
         distribution: plugin.distribution.clone(),
         license_status: plugin.license_status.clone(),
         permissions: plugin.permissions.clone(),
+        agents: plugin.agents.clone(),
     }
 }
 
-pub fn load_policy() -> anyhow::Result<PolicyFile> {
-    let home = std::env::var("HOME")?;
-    let path = PathBuf::from(home).join(".config/pater/policy.toml");
-    if !path.exists() {
-        return Ok(PolicyFile {
-            general: PolicyGeneral::default(),
-        });
-    }
-    let raw = std::fs::read_to_string(path)?;
-    Ok(toml::from_str(&raw)?)
-}
-
 pub fn update_plugins(
     state: &mut State,
     markets: &[MarketRef],
@@ -48,9 +39,18 @@ pub fn update_plugins(
     allow_permission_increase: bool,
     policy: &PolicyFile,
 ) -> anyhow::Result<Vec<UpdateReport>> {
+    let selected = match only {
+        Some(target) => match resolve_installed(state, target)? {
+            Some(key) => Some(key),
+            None => return Ok(Vec::new()),
+        },
+        None => None,
+    };
     let mut reports = Vec::new();
     for installed in &mut state.installed {
-        if only.is_some_and(|name| name != installed.name) {
+        if selected.as_ref().is_some_and(|(name, market)| {
+            name != &installed.name || market != &installed.marketplace
+        }) {
             continue;
         }
         let latest = show_plugin(
@@ -60,41 +60,43 @@ pub fn update_plugins(
             policy,
         )?;
         enforce_policy_for_plugin(policy, &latest)?;
-        let old_permissions: HashSet<_> = installed.permissions.iter().cloned().collect();
-        let new_permissions: HashSet<_> = latest.permissions.iter().cloned().collect();
-        let added_permissions: Vec<String> = new_permissions
-            .difference(&old_permissions)
-            .cloned()
-            .collect();
-
-        if !added_permissions.is_empty() && !allow_permission_increase {
+        let delta = permission_delta(&installed.permissions, &latest.permissions);
+
+        if !delta.is_empty() && !allow_permission_increase {
             reports.push(UpdateReport {
                 name: installed.name.clone(),
                 status: "blocked_permission_increase".to_string(),
                 old_version: installed.version.clone(),
                 new_version: latest.version.clone(),
-                added_permissions,
+                added_permissions: delta.added,
+                broadened_permissions: delta.broadened,
             });
             continue;
         }
 
-        let changed =
-            installed.version != latest.version || installed.permissions != latest.permissions;
+        let changed = installed.version != latest.version
+            || installed.permissions != latest.permissions
+            || installed.agents != latest.agents;
         if changed {
             let report = UpdateReport {
                 name: installed.name.clone(),
                 status: "updated".to_string(),
                 old_version: installed.version.clone(),
                 new_version: latest.version.clone(),
-                added_permissions,
+                added_permissions: delta.added,
+                broadened_permissions: delta.broadened,
             };
             installed.version = latest.version.clone();
             installed.permissions = latest.permissions.clone();
+            installed.agents = latest.agents.clone();
             installed.source = latest.source.clone();
             installed.marketplace_source = latest.marketplace_source.clone();
             if let Ok(src) = rack::resolve_plugin_path(&latest.marketplace_source, &latest.source) {
-                if let Ok(p) = materialize_plugin(&installed.name, &src) {
+                if let Ok((p, hash)) =
+                    materialize_plugin(&installed.marketplace, &installed.name, &src)
+                {
                     installed.local_path = p.to_string_lossy().to_string();
+                    installed.store_hash = Some(hash);
                 }
             }
             reports.push(report);
@@ -105,6 +107,7 @@ pub fn update_plugins(
                 old_version: installed.version.clone(),
                 new_version: latest.version.clone(),
                 added_permissions: vec![],
+                broadened_permissions: vec![],
             });
         }
     }
@@ -195,6 +198,7 @@ pub fn show_plugin(
     marketplace: Option<&str>,
     policy: &PolicyFile,
 ) -> anyhow::Result<DiscoverItem> {
+    let mut found: Vec<DiscoverItem> = Vec::new();
     for m in markets {
         let Ok(loaded) = checked_load_marketplace(&m.source, policy) else {
             continue;
@@ -202,11 +206,53 @@ pub fn show_plugin(
         if marketplace.is_some_and(|filter| loaded.name != filter) {
             continue;
         }
+        if found.iter().any(|f| f.marketplace == loaded.name) {
+            continue;
+        }
         if let Ok(p) = rack::show(&loaded, name) {
-            return Ok(discover_item_from_plugin(&loaded.name, &m.source, p));
+            found.push(discover_item_from_plugin(&loaded.name, &m.source, p));
         }
     }
-    anyhow::bail!("plugin not found: {}", name)
+    if found.len() > 1 {
+        anyhow::bail!(
+            "ambiguous plugin name: {} is offered by {}; use name@marketplace",
+            name,
+            found
+                .iter()
+                .map(|f| f.marketplace.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    found
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("plugin not found: {}", name))
+}
+
+/// Resolves `name[@marketplace]` to the `(name, marketplace)` key of one
+/// installed plugin. A bare name installed from several marketplaces is an
+/// error rather than a silent match on all of them.
+pub fn resolve_installed(state: &State, target: &str) -> anyhow::Result<Option<(String, String)>> {
+    let (name, market) = parse_target(target);
+    let matches: Vec<_> = state
+        .installed
+        .iter()
+        .filter(|p| p.name == name && market.as_ref().is_none_or(|m| m == &p.marketplace))
+        .collect();
+    if matches.len() > 1 {
+        anyhow::bail!(
+            "ambiguous plugin name: {} is installed from {}; use name@marketplace",
+            name,
+            matches
+                .iter()
+                .map(|p| p.marketplace.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(matches
+        .first()
+        .map(|p| (p.name.clone(), p.marketplace.clone())))
 }
 
 pub fn checked_load_marketplace(
@@ -222,77 +268,353 @@ pub fn checked_load_marketplace(
     rack::load_marketplace(source)
 }
 
+/// Errors when `agent` is a specific adapter the plugin does not list as
+/// supported. Plugins without `agents` metadata are compatible with all.
+pub fn check_agent_compat(p: &DiscoverItem, agent: &str) -> anyhow::Result<()> {
+    if agent != ALL_ADAPTERS && !p.agents.is_empty() && !p.agents.iter().any(|a| a == agent) {
+        anyhow::bail!(
+            "plugin {} does not support agent {} (supports: {})",
+            p.name,
+            agent,
+            p.agents.join(", ")
+        );
+    }
+    Ok(())
+}
+
 pub fn policy_eval_for_plugin(
     policy: &PolicyFile,
     p: &DiscoverItem,
-    agent: AdapterTarget,
+    agent: &str,
+    explain: bool,
 ) -> PolicyEvalReport {
-    match enforce_policy_for_plugin(policy, p) {
-        Ok(_) => PolicyEvalReport {
-            plugin: p.name.clone(),
-            agent: format!("{:?}", agent).to_lowercase(),
-            allowed: true,
-            reason: "allowed".to_string(),
-        },
-        Err(e) => PolicyEvalReport {
-            plugin: p.name.clone(),
-            agent: format!("{:?}", agent).to_lowercase(),
-            allowed: false,
-            reason: e.to_string(),
-        },
+    let rules = policy.for_agent(agent);
+    let verdict = check_agent_compat(p, agent).and_then(|_| enforce_rules(rules, p, &Lookup::Live));
+    let (allowed, reason) = match verdict {
+        Ok(_) => (true, "allowed".to_string()),
+        Err(e) => (false, e.to_string()),
+    };
+    PolicyEvalReport {
+        plugin: p.name.clone(),
+        agent: agent.to_string(),
+        allowed,
+        reason,
+        supported_agents: p.agents.clone(),
+        blocked_permissions: blocked_permissions(rules, &p.permissions),
+        trace: explain.then(|| policy_trace(rules, p, agent)),
     }
 }
 
-pub fn enforce_policy_for_plugin(policy: &PolicyFile, p: &DiscoverItem) -> anyhow::Result<()> {
-    if policy.general.denied_plugins.iter().any(|x| x == &p.name) {
-        anyhow::bail!("policy denied plugin: {}", p.name);
-    }
-    if !policy.general.allowed_sources.is_empty()
-        && !policy
-            .general
-            .allowed_sources
+/// What one policy rule decided about a plugin, with a human-readable why.
+enum RuleOutcome {
+    Pass(String),
+    Deny(String),
+    /// The rule has nothing to check for this plugin or policy.
+    Skip(String),
+}
+
+/// Where rules that need more than the plugin's listing look it up.
+enum Lookup<'a> {
+    /// Resolve the plugin source and verify the marketplace signature.
+    Live,
+    /// Sync: read only the materialized runtime dir, so nothing is fetched
+    /// or cloned; the signature was checked when the plugin was installed.
+    Runtime(&'a Path),
+}
+
+type PolicyRule = fn(&PolicyGeneral, &DiscoverItem, &Lookup) -> anyhow::Result<RuleOutcome>;
+
+/// Install-time rules in evaluation order. `enforce_policy_for_plugin`
+/// stops at the first denial; `policy eval --explain` runs them all.
+const POLICY_RULES: &[(&str, PolicyRule)] = &[
+    ("denied_plugins", rule_denied_plugins),
+    ("allowed_sources", rule_allowed_sources),
+    ("signed_marketplace", rule_signed_marketplace),
+    ("permissions", rule_permissions),
+    ("external_reference", rule_external_reference),
+    ("unknown_license", rule_unknown_license),
+];
+
+fn rule_denied_plugins(
+    policy: &PolicyGeneral,
+    p: &DiscoverItem,
+    _: &Lookup,
+) -> anyhow::Result<RuleOutcome> {
+    let denied = &policy.denied_plugins;
+    Ok(if denied.is_empty() {
+        RuleOutcome::Skip("no denied_plugins".to_string())
+    } else if plugin_list_matches(denied, &p.name, &p.marketplace) {
+        RuleOutcome::Deny(format!("policy denied plugin: {}", p.name))
+    } else {
+        RuleOutcome::Pass("not in denied_plugins".to_string())
+    })
+}
+
+fn rule_allowed_sources(
+    policy: &PolicyGeneral,
+    p: &DiscoverItem,
+    _: &Lookup,
+) -> anyhow::Result<RuleOutcome> {
+    let Some(allowed) = &policy.allowed_sources else {
+        return Ok(RuleOutcome::Skip("no allowed_sources".to_string()));
+    };
+    Ok(
+        if allowed
             .iter()
             .any(|s| source_matches_allowed(&p.marketplace_source, s))
-    {
-        anyhow::bail!("policy denied source: {}", p.marketplace_source);
+        {
+            RuleOutcome::Pass(format!("source {} is allowed", p.marketplace_source))
+        } else {
+            RuleOutcome::Deny(format!("policy denied source: {}", p.marketplace_source))
+        },
+    )
+}
+
+fn rule_signed_marketplace(
+    policy: &PolicyGeneral,
+    p: &DiscoverItem,
+    lookup: &Lookup,
+) -> anyhow::Result<RuleOutcome> {
+    if !policy.require_signed_marketplace {
+        return Ok(RuleOutcome::Skip(
+            "require_signed_marketplace is off".to_string(),
+        ));
     }
-    if p.permissions
+    if let Lookup::Runtime(_) = lookup {
+        return Ok(RuleOutcome::Skip(
+            "signature is checked when the marketplace loads, not on sync".to_string(),
+        ));
+    }
+    Ok(match verify_marketplace_signature(&p.marketplace_source) {
+        Ok(true) => RuleOutcome::Pass(format!(
+            "marketplace {} is signed by a trusted key",
+            p.marketplace_source
+        )),
+        Ok(false) => RuleOutcome::Deny(format!(
+            "policy requires a signed marketplace: signature verification failed: {}",
+            p.marketplace_source
+        )),
+        Err(e) => RuleOutcome::Deny(format!(
+            "policy requires a signed marketplace: cannot verify {}: {}",
+            p.marketplace_source, e
+        )),
+    })
+}
+
+fn rule_permissions(
+    policy: &PolicyGeneral,
+    p: &DiscoverItem,
+    _: &Lookup,
+) -> anyhow::Result<RuleOutcome> {
+    if p.permissions.is_empty() {
+        return Ok(RuleOutcome::Skip(
+            "plugin declares no permissions".to_string(),
+        ));
+    }
+    let invalid = p
+        .permissions
         .iter()
-        .any(|perm| policy.general.blocked_permissions.contains(perm))
-    {
-        anyhow::bail!("policy blocked permission in plugin: {}", p.name);
+        .filter(|perm| Permission::parse(perm).is_err())
+        .cloned()
+        .collect::<Vec<_>>();
+    if !invalid.is_empty() {
+        return Ok(RuleOutcome::Deny(format!(
+            "policy rejected invalid permission in plugin: {} ({})",
+            p.name,
+            invalid.join(", ")
+        )));
     }
+    let blocked = blocked_permissions(policy, &p.permissions);
+    Ok(if blocked.is_empty() {
+        RuleOutcome::Pass(format!("allowed: {}", p.permissions.join(", ")))
+    } else {
+        RuleOutcome::Deny(format!(
+            "policy blocked permission in plugin: {} ({})",
+            p.name,
+            blocked.join(", ")
+        ))
+    })
+}
 
-    if p.distribution.as_deref() == Some("external-reference-only")
-        && !policy.general.allow_external_reference_installs
-        && !policy
-            .general
-            .allow_external_reference_plugins
-            .iter()
-            .any(|x| x == &p.name)
-    {
-        anyhow::bail!(
-            "policy blocked external-reference-only plugin: {} (set allow_external_reference_installs=true or add plugin to allow_external_reference_plugins)",
+fn rule_external_reference(
+    policy: &PolicyGeneral,
+    p: &DiscoverItem,
+    _: &Lookup,
+) -> anyhow::Result<RuleOutcome> {
+    Ok(
+        if p.distribution.as_deref() != Some("external-reference-only") {
+            RuleOutcome::Skip("not an external-reference-only plugin".to_string())
+        } else if policy.allow_external_reference_installs {
+            RuleOutcome::Pass("allow_external_reference_installs is on".to_string())
+        } else if plugin_list_matches(
+            &policy.allow_external_reference_plugins,
+            &p.name,
+            &p.marketplace,
+        ) {
+            RuleOutcome::Pass("listed in allow_external_reference_plugins".to_string())
+        } else {
+            RuleOutcome::Deny(format!(
+                "policy blocked external-reference-only plugin: {} (set allow_external_reference_installs=true or add plugin to allow_external_reference_plugins)",
+                p.name
+            ))
+        },
+    )
+}
+
+fn rule_unknown_license(
+    policy: &PolicyGeneral,
+    p: &DiscoverItem,
+    lookup: &Lookup,
+) -> anyhow::Result<RuleOutcome> {
+    if !policy.block_unknown_licenses {
+        return Ok(RuleOutcome::Skip(
+            "block_unknown_licenses is off".to_string(),
+        ));
+    }
+    let cls = match lookup {
+        Lookup::Live => classify_license(&rack::resolve_plugin_path(
+            &p.marketplace_source,
+            &p.source,
+        )?),
+        Lookup::Runtime(dir) => classify_license(dir),
+    };
+    Ok(if cls != "unknown" {
+        RuleOutcome::Pass(format!("license is {}", cls))
+    } else if plugin_list_matches(
+        &policy.allow_unknown_license_plugins,
+        &p.name,
+        &p.marketplace,
+    ) {
+        RuleOutcome::Pass("listed in allow_unknown_license_plugins".to_string())
+    } else {
+        RuleOutcome::Deny(format!(
+            "policy blocked unknown-license plugin: {} (add to allow_unknown_license_plugins to override)",
             p.name
-        );
+        ))
+    })
+}
+
+fn trace_entry(rule: &str, outcome: anyhow::Result<RuleOutcome>) -> PolicyRuleTrace {
+    let (outcome, detail) = match outcome {
+        Ok(RuleOutcome::Pass(d)) => ("pass", d),
+        Ok(RuleOutcome::Deny(d)) => ("deny", d),
+        Ok(RuleOutcome::Skip(d)) => ("skip", d),
+        Err(e) => ("error", e.to_string()),
+    };
+    PolicyRuleTrace {
+        rule: rule.to_string(),
+        outcome: outcome.to_string(),
+        detail,
+    }
+}
+
+/// Every rule's outcome for `p`, starting with agent compatibility.
+pub fn policy_trace(policy: &PolicyGeneral, p: &DiscoverItem, agent: &str) -> Vec<PolicyRuleTrace> {
+    let compat = match check_agent_compat(p, agent) {
+        Ok(_) if p.agents.is_empty() => RuleOutcome::Skip("plugin lists no agents".to_string()),
+        Ok(_) => RuleOutcome::Pass(format!("supports {}", agent)),
+        Err(e) => RuleOutcome::Deny(e.to_string()),
+    };
+    let mut trace = vec![trace_entry("agents", Ok(compat))];
+    trace.extend(
+        POLICY_RULES
+            .iter()
+            .map(|(name, rule)| trace_entry(name, rule(policy, p, &Lookup::Live))),
+    );
+    trace
+}
+
+/// The installed copy of a plugin as a `DiscoverItem`: permissions and
+/// agents as installed, distribution and license from its marketplace
+/// entry (`listed`) when that still loads.
+fn installed_item(installed: &InstalledPlugin, listed: Option<&DiscoverItem>) -> DiscoverItem {
+    DiscoverItem {
+        marketplace: installed.marketplace.clone(),
+        marketplace_source: installed.marketplace_source.clone(),
+        name: installed.name.clone(),
+        description: String::new(),
+        version: installed.version.clone(),
+        source: installed.source.clone(),
+        distribution: listed.and_then(|l| l.distribution.clone()),
+        license_status: listed.and_then(|l| l.license_status.clone()),
+        permissions: installed.permissions.clone(),
+        agents: installed.agents.clone(),
     }
+}
 
-    if policy.general.block_unknown_licenses {
-        let cls = classify_plugin_license(p)?;
-        if cls == "unknown"
-            && !policy
-                .general
-                .allow_unknown_license_plugins
-                .iter()
-                .any(|x| x == &p.name)
-        {
-            anyhow::bail!(
-                "policy blocked unknown-license plugin: {} (add to allow_unknown_license_plugins to override)",
-                p.name
-            );
+/// Re-runs the install-time policy rules over every installed plugin. A
+/// plugin whose marketplace no longer loads under the policy (e.g. its
+/// signature fails) is a violation. With `quarantine`, violators are marked
+/// quarantined and plugins that pass again are released; the caller resyncs.
+pub fn policy_audit(state: &mut State, policy: &PolicyFile, quarantine: bool) -> PolicyAuditReport {
+    let mut plugins = Vec::new();
+    for installed in &mut state.installed {
+        let verdict = match checked_load_marketplace(&installed.marketplace_source, policy) {
+            Ok(loaded) => {
+                // A plugin no longer listed is judged on what was recorded.
+                let listed = rack::show(&loaded, &installed.name).ok().map(|p| {
+                    discover_item_from_plugin(&loaded.name, &installed.marketplace_source, p)
+                });
+                enforce_policy_for_plugin(policy, &installed_item(installed, listed.as_ref())).err()
+            }
+            Err(e) => Some(anyhow::anyhow!(
+                "policy audit cannot load marketplace {}: {}",
+                installed.marketplace_source,
+                e
+            )),
+        };
+        if quarantine {
+            installed.quarantined = verdict.as_ref().map(|e| e.to_string());
         }
+        plugins.push(PolicyAuditEntry {
+            plugin: installed.name.clone(),
+            marketplace: installed.marketplace.clone(),
+            allowed: verdict.is_none(),
+            reason: verdict.map_or_else(|| "allowed".to_string(), |e| e.to_string()),
+            quarantined: installed.quarantined.is_some(),
+        });
     }
+    PolicyAuditReport {
+        checked: plugins.len(),
+        violations: plugins.iter().filter(|p| !p.allowed).count(),
+        plugins,
+    }
+}
 
+/// Whether sync may expose an installed plugin to `agent`, and why not.
+/// Only agents with an `[agent.<name>]` policy section are re-checked;
+/// `general` already gated the install. Works from the install record, the
+/// cached listing and the runtime dir only.
+pub fn agent_policy_allows(
+    policy: &PolicyFile,
+    installed: &InstalledPlugin,
+    listed: Option<&rack::Plugin>,
+    runtime_dir: &Path,
+    agent: &str,
+) -> anyhow::Result<()> {
+    let Some(rules) = policy.agents.get(agent) else {
+        return Ok(());
+    };
+    let listed = listed.map(|l| {
+        discover_item_from_plugin(&installed.marketplace, &installed.marketplace_source, l)
+    });
+    enforce_rules(
+        rules,
+        &installed_item(installed, listed.as_ref()),
+        &Lookup::Runtime(runtime_dir),
+    )
+}
+
+pub fn enforce_policy_for_plugin(policy: &PolicyFile, p: &DiscoverItem) -> anyhow::Result<()> {
+    enforce_rules(&policy.general, p, &Lookup::Live)
+}
+
+fn enforce_rules(policy: &PolicyGeneral, p: &DiscoverItem, lookup: &Lookup) -> anyhow::Result<()> {
+    for (_, rule) in POLICY_RULES {
+        if let RuleOutcome::Deny(reason) = rule(policy, p, lookup)? {
+            anyhow::bail!(reason);
+        }
+    }
     Ok(())
 }
 
@@ -300,8 +622,7 @@ fn normalize_license_token(raw: &str) -> String {
     raw.trim().to_ascii_uppercase().replace(' ', "-")
 }
 
-fn classify_plugin_license(p: &DiscoverItem) -> anyhow::Result<&'static str> {
-    let src = rack::resolve_plugin_path(&p.marketplace_source, &p.source)?;
+fn classify_license(src: &Path) -> &'static str {
     let mut tokens = Vec::new();
 
     for name in ["LICENSE", "LICENSE.md", "COPYING"] {
@@ -326,20 +647,20 @@ fn classify_plugin_license(p: &DiscoverItem) -> anyhow::Result<&'static str> {
 
     let joined = tokens.join("\n");
     if joined.is_empty() {
-        return Ok("unknown");
+        return "unknown";
     }
 
     let permissive_needles = ["MIT", "APACHE", "BSD", "ISC", "UNLICENSE", "CC0"];
     if permissive_needles.iter().any(|n| joined.contains(n)) {
-        return Ok("permissive");
+        return "permissive";
     }
 
     let copyleft_needles = ["GPL", "AGPL", "LGPL", "MPL"];
     if copyleft_needles.iter().any(|n| joined.contains(n)) {
-        return Ok("copyleft");
+        return "copyleft";
     }
 
-    Ok("unknown")
+    "unknown"
 }
 
 pub fn run_rack_license_audit(marketplace_source: &str) -> String {
@@ -0,0 +1,191 @@
+use crate::domain::constants::{
+    LOCKFILE_SCHEMA_VERSION, REGISTRY_SCHEMA_VERSION, STATE_SCHEMA_VERSION,
+};
+use std::path::{Path, PathBuf};
+
+/// Upgrades a document by exactly one schema version.
+pub type Migration = fn(serde_json::Value) -> anyhow::Result<serde_json::Value>;
+
+pub struct SchemaSpec {
+    pub kind: &'static str,
+    pub version_key: &'static str,
+    pub current: u32,
+    /// `migrations[n]` upgrades a document from version `n` to `n + 1`.
+    pub migrations: &'static [Migration],
+}
+
+pub const STATE_SCHEMA: SchemaSpec = SchemaSpec {
+    kind: "state.json",
+    version_key: "schema_version",
+    current: STATE_SCHEMA_VERSION,
+    migrations: &[state_v0_to_v1],
+};
+
+/// Lockfile is rewritten from state and never read back, so it only needs
+/// a version gate.
+pub const LOCKFILE_SCHEMA: SchemaSpec = SchemaSpec {
+    kind: "pater.lock",
+    version_key: "version",
+    current: LOCKFILE_SCHEMA_VERSION,
+    migrations: &[],
+};
+
+/// Registry is regenerated on every sync, so it only needs a version gate.
+pub const REGISTRY_SCHEMA: SchemaSpec = SchemaSpec {
+    kind: "registry.json",
+    version_key: "schemaVersion",
+    current: REGISTRY_SCHEMA_VERSION,
+    migrations: &[],
+};
+
+pub fn document_version(spec: &SchemaSpec, value: &serde_json::Value) -> u32 {
+    value
+        .get(spec.version_key)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32
+}
+
+pub fn ensure_supported(spec: &SchemaSpec, version: u32) -> anyhow::Result<()> {
+    if version > spec.current {
+        anyhow::bail!(
+            "{} schema version {} is newer than supported version {}; upgrade pater to read it",
+            spec.kind,
+            version,
+            spec.current
+        );
+    }
+    Ok(())
+}
+
+/// A corrupted file may hold any JSON value; migrations need an object.
+fn as_object<'a>(
+    kind: &str,
+    value: &'a mut serde_json::Value,
+) -> anyhow::Result<&'a mut serde_json::Map<String, serde_json::Value>> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a JSON object; fix or remove it", kind))
+}
+
+/// Returns the migrated document and the version it was read at.
+pub fn migrate_value(
+    spec: &SchemaSpec,
+    mut value: serde_json::Value,
+) -> anyhow::Result<(serde_json::Value, u32)> {
+    as_object(spec.kind, &mut value)?;
+    let from = document_version(spec, &value);
+    ensure_supported(spec, from)?;
+    for version in from..spec.current {
+        let step = spec.migrations.get(version as usize).ok_or_else(|| {
+            anyhow::anyhow!(
+                "no migration for {} from schema version {}",
+                spec.kind,
+                version
+            )
+        })?;
+        value = step(value)?;
+        as_object(spec.kind, &mut value)?
+            .insert(spec.version_key.to_string(), serde_json::json!(version + 1));
+    }
+    Ok((value, from))
+}
+
+/// Reads `path`, upgrades it in place when it is older than `spec.current`
+/// and keeps a copy of the original next to it.
+pub fn load_migrated(path: &Path, spec: &SchemaSpec) -> anyhow::Result<Option<serde_json::Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(path)?;
+    let value: serde_json::Value = serde_json::from_str(&raw)?;
+    let (migrated, from) = migrate_value(spec, value)?;
+    if from < spec.current {
+        backup_file(path, from)?;
+        std::fs::write(path, serde_json::to_string_pretty(&migrated)?)?;
+    }
+    Ok(Some(migrated))
+}
+
+/// Refuses to touch a file written by a newer pater.
+pub fn check_existing(path: &Path, spec: &SchemaSpec) -> anyhow::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let raw = std::fs::read_to_string(path)?;
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(&raw) else {
+        return Ok(());
+    };
+    ensure_supported(spec, document_version(spec, &value))
+}
+
+pub fn backup_file(path: &Path, version: u32) -> anyhow::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let backup = path.with_file_name(format!("{}.v{}.bak", name, version));
+    std::fs::copy(path, &backup)?;
+    Ok(backup)
+}
+
+fn fill_installed_defaults(entries: &mut [serde_json::Value]) {
+    for entry in entries {
+        let Some(obj) = entry.as_object_mut() else {
+            continue;
+        };
+        obj.entry("marketplace_source")
+            .or_insert_with(|| serde_json::json!(""));
+        obj.entry("local_path")
+            .or_insert_with(|| serde_json::json!(""));
+        obj.entry("permissions")
+            .or_insert_with(|| serde_json::json!([]));
+        obj.entry("scope")
+            .or_insert_with(|| serde_json::json!("user"));
+    }
+}
+
+/// v0 had no version field and relied on serde defaults for later fields.
+fn state_v0_to_v1(mut value: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    let obj = as_object(STATE_SCHEMA.kind, &mut value)?;
+    obj.entry("marketplaces")
+        .or_insert_with(|| serde_json::json!([]));
+    obj.entry("installed")
+        .or_insert_with(|| serde_json::json!([]));
+    if let Some(installed) = obj.get_mut("installed").and_then(|x| x.as_array_mut()) {
+        fill_installed_defaults(installed);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migrate_value, STATE_SCHEMA};
+
+    #[test]
+    fn unversioned_state_is_upgraded_with_defaults() {
+        let old = serde_json::json!({
+            "marketplaces": [],
+            "installed": [{"name": "demo", "marketplace": "m", "source": "./demo"}]
+        });
+        let (migrated, from) = migrate_value(&STATE_SCHEMA, old).unwrap();
+        assert_eq!(from, 0);
+        assert_eq!(migrated["schema_version"], STATE_SCHEMA.current);
+        assert_eq!(migrated["installed"][0]["scope"], "user");
+        assert_eq!(migrated["installed"][0]["local_path"], "");
+    }
+
+    #[test]
+    fn non_object_documents_are_errors_not_panics() {
+        for corrupt in [serde_json::json!([1, 2]), serde_json::json!("state")] {
+            let err = migrate_value(&STATE_SCHEMA, corrupt).unwrap_err();
+            assert!(err.to_string().contains("not a JSON object"));
+        }
+    }
+
+    #[test]
+    fn newer_state_is_refused() {
+        let future = serde_json::json!({"schema_version": STATE_SCHEMA.current + 1});
+        let err = migrate_value(&STATE_SCHEMA, future).unwrap_err();
+        assert!(err.to_string().contains("newer than supported"));
+    }
+}
@@ -2,13 +2,19 @@
 //!
 //! ## Service map
 //! - `marketplace.rs` — discover/show/recommend/policy enforcement/update flow.
-//! - `adapters.rs` — runtime-first adapter bridge sync/smoke/doctor.
+//! - `adapters/` — `Adapter` trait + registry; runtime-first bridge sync/smoke/doctor/unsync.
 //! - `trust.rs` — trusted key storage + signature verification.
 //! - `rack_ops.rs` — rack maintainer workflows (sync/audit/sign).
 //! - `authoring.rs` — plugin/skill/subagent/hook/mcp authoring mutations.
 //! - `storage.rs` — local state/lock persistence + runtime paths + audit log.
+//! - `migrations.rs` — schema versions for state/lock/registry files + state upgrade steps.
+//! - `gc.rs` — pruning of unreferenced runtime plugins and caches.
+//! - `store.rs` — content-addressed plugin store + hardlinked runtime views.
+//! - `uninstall.rs` — `pater self uninstall` (unsync all adapters + delete pater data).
 //! - `release_check.rs` — release report assembly.
-//! - `policy.rs` — source canonicalization and allowlist matching.
+//! - `policy.rs` — layered policy loading/merging, effective origins, source matching.
+//! - `permissions.rs` — typed plugin permissions, policy rules over them, update deltas.
+//! - `glob.rs` — small `*`/`?`/`**` matcher shared by ignore files and policy lists.
 //! - `output.rs` — JSON/text output helpers.
 //!
 //! ## Conventions
@@ -20,13 +26,20 @@
 //! - Policy checks must execute before install/apply/ensure.
 //! - `check` path must stay read-only for rack license audit.
 //! - Trust verification gates marketplace loading when policy requires signatures.
+//! - Files written by a newer pater (higher schema version) are never overwritten.
 
 pub mod adapters;
 pub mod authoring;
+pub mod gc;
+pub mod glob;
 pub mod marketplace;
+pub mod migrations;
 pub mod output;
+pub mod permissions;
 pub mod policy;
 pub mod rack_ops;
 pub mod release_check;
 pub mod storage;
+pub mod store;
 pub mod trust;
+pub mod uninstall;
@@ -0,0 +1,261 @@
+//! Typed plugin permissions.
+//!
+//! Marketplace `permissions` use a small vocabulary: `fs:read:<glob>`,
+//! `fs:write:<glob>`, `net:<host>`, `exec:<cmd>` and `secrets:<name>`, where
+//! the scope may itself be a glob (`net:*.github.com`). Strings outside the
+//! vocabulary (`filesystem.read`) are kept as legacy permissions and compared
+//! whole. A string that starts like a typed permission but does not parse is
+//! an error.
+//!
+//! Policy entries (`blocked_permissions`, `allowed_permissions`) are rules in
+//! the same syntax; a rule may also name just a category (`fs`, `fs:read`,
+//! `net`) to match every scope in it.
+
+use crate::services::glob::glob_match;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionKind {
+    FsRead,
+    FsWrite,
+    Net,
+    Exec,
+    Secrets,
+    Legacy,
+}
+
+/// A parsed permission: what it grants and how widely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permission {
+    pub kind: PermissionKind,
+    /// Path glob, host, command or secret name; the whole string for
+    /// legacy permissions.
+    pub scope: String,
+}
+
+/// A policy entry: the kinds it applies to and a glob over their scopes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionRule {
+    kinds: Vec<PermissionKind>,
+    scope: String,
+}
+
+const VOCABULARY: &str = "fs:read:<glob>, fs:write:<glob>, net:<host>, exec:<cmd>, secrets:<name>";
+
+/// Kinds a category prefix selects, or `None` if `raw` is not a typed prefix.
+fn category_kinds(raw: &str) -> Option<Vec<PermissionKind>> {
+    Some(match raw {
+        "fs" => vec![PermissionKind::FsRead, PermissionKind::FsWrite],
+        "fs:read" => vec![PermissionKind::FsRead],
+        "fs:write" => vec![PermissionKind::FsWrite],
+        "net" => vec![PermissionKind::Net],
+        "exec" => vec![PermissionKind::Exec],
+        "secrets" => vec![PermissionKind::Secrets],
+        _ => return None,
+    })
+}
+
+fn invalid(raw: &str) -> anyhow::Error {
+    anyhow::anyhow!("invalid permission {} (expected {})", raw, VOCABULARY)
+}
+
+impl Permission {
+    pub fn parse(raw: &str) -> anyhow::Result<Permission> {
+        let (category, rest) = raw.split_once(':').unwrap_or((raw, ""));
+        let (kind, scope) = match category {
+            "fs" => match rest.split_once(':') {
+                Some(("read", path)) => (PermissionKind::FsRead, path),
+                Some(("write", path)) => (PermissionKind::FsWrite, path),
+                _ => return Err(invalid(raw)),
+            },
+            "net" => (PermissionKind::Net, rest),
+            "exec" => (PermissionKind::Exec, rest),
+            "secrets" => (PermissionKind::Secrets, rest),
+            _ => {
+                return Ok(Permission {
+                    kind: PermissionKind::Legacy,
+                    scope: raw.to_string(),
+                })
+            }
+        };
+        if scope.is_empty() {
+            return Err(invalid(raw));
+        }
+        Ok(Permission {
+            kind,
+            scope: scope.to_string(),
+        })
+    }
+
+    /// Whether granting `self` already grants `other`: same kind, and
+    /// `self`'s scope glob matches `other`'s scope taken literally.
+    /// Legacy permissions only cover themselves.
+    pub fn covers(&self, other: &Permission) -> bool {
+        self.kind == other.kind
+            && if self.kind == PermissionKind::Legacy {
+                self.scope == other.scope
+            } else {
+                glob_match(&self.scope, &other.scope)
+            }
+    }
+}
+
+impl PermissionRule {
+    /// Parses a policy entry. Category-only entries match every scope;
+    /// legacy entries stay globs over the whole permission string.
+    pub fn parse(raw: &str) -> anyhow::Result<PermissionRule> {
+        if let Some(kinds) = category_kinds(raw) {
+            return Ok(PermissionRule {
+                kinds,
+                scope: "**".to_string(),
+            });
+        }
+        let p = Permission::parse(raw)?;
+        Ok(PermissionRule {
+            kinds: vec![p.kind],
+            scope: p.scope,
+        })
+    }
+
+    pub fn matches(&self, p: &Permission) -> bool {
+        self.kinds.contains(&p.kind) && glob_match(&self.scope, &p.scope)
+    }
+
+    /// Whether everything `narrower` matches is matched by `self`, taking
+    /// `narrower`'s scope literally. Used to narrow allow lists across layers.
+    pub fn covers(&self, narrower: &PermissionRule) -> bool {
+        narrower.kinds.iter().all(|k| self.kinds.contains(k))
+            && glob_match(&self.scope, &narrower.scope)
+    }
+}
+
+/// Whether the policy entry `rule` matches the plugin permission `perm`.
+/// If either does not parse they match only when the strings are equal, so
+/// a malformed permission cannot slip past a rule naming it.
+pub fn rule_matches(rule: &str, perm: &str) -> bool {
+    match (PermissionRule::parse(rule), Permission::parse(perm)) {
+        (Ok(rule), Ok(perm)) => rule.matches(&perm),
+        _ => rule == perm,
+    }
+}
+
+/// Whether the policy entry `wider` matches everything `narrower` does;
+/// entries that do not parse cover only themselves.
+pub fn rule_covers(wider: &str, narrower: &str) -> bool {
+    match (
+        PermissionRule::parse(wider),
+        PermissionRule::parse(narrower),
+    ) {
+        (Ok(wider), Ok(narrower)) => wider.covers(&narrower),
+        _ => wider == narrower,
+    }
+}
+
+/// How a plugin's permissions grew between two versions.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PermissionDelta {
+    /// New permissions of a kind the old version did not have.
+    pub added: Vec<String>,
+    /// New permissions widening a kind the old version already had, e.g.
+    /// `fs:read:src/**` becoming `fs:read:**`.
+    pub broadened: Vec<String>,
+}
+
+impl PermissionDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.broadened.is_empty()
+    }
+}
+
+/// Compares permission lists. A new permission counts only if no old one
+/// covers it, so narrowing or reordering is not an increase. Permissions
+/// that do not parse are compared as plain strings.
+pub fn permission_delta(old: &[String], new: &[String]) -> PermissionDelta {
+    let parse = |raw: &String| {
+        Permission::parse(raw).unwrap_or(Permission {
+            kind: PermissionKind::Legacy,
+            scope: raw.clone(),
+        })
+    };
+    let old = old.iter().map(parse).collect::<Vec<_>>();
+    let mut delta = PermissionDelta::default();
+    for raw in new {
+        let p = parse(raw);
+        if old.iter().any(|o| o.covers(&p)) {
+            continue;
+        }
+        if old
+            .iter()
+            .any(|o| o.kind == p.kind && o.kind != PermissionKind::Legacy)
+        {
+            delta.broadened.push(raw.clone());
+        } else {
+            delta.added.push(raw.clone());
+        }
+    }
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_vocabulary_and_keeps_legacy_strings() {
+        let p = Permission::parse("fs:read:src/**").expect("fs");
+        assert_eq!(p.kind, PermissionKind::FsRead);
+        assert_eq!(p.scope, "src/**");
+        assert_eq!(
+            Permission::parse("net:api.github.com").expect("net").kind,
+            PermissionKind::Net
+        );
+        assert_eq!(
+            Permission::parse("filesystem.read").expect("legacy").kind,
+            PermissionKind::Legacy
+        );
+        assert!(Permission::parse("fs:delete:x").is_err());
+        assert!(Permission::parse("net:").is_err());
+        assert!(Permission::parse("secrets").is_err());
+    }
+
+    #[test]
+    fn rules_match_by_category_and_scope() {
+        assert!(rule_matches("fs", "fs:write:/etc/hosts"));
+        assert!(rule_matches("fs:read", "fs:read:**"));
+        assert!(!rule_matches("fs:read", "fs:write:x"));
+        assert!(rule_matches("net:*.github.com", "net:api.github.com"));
+        assert!(!rule_matches("net:*.github.com", "net:example.com"));
+        assert!(rule_matches("fs:read:src/**", "fs:read:src/lib/*.rs"));
+        assert!(!rule_matches("fs:read:src/**", "fs:read:**"));
+        assert!(rule_matches("filesystem.*", "filesystem.read"));
+        assert!(!rule_matches("net", "fs:read:x"));
+        assert!(rule_covers("fs", "fs:read:src/**"));
+        assert!(!rule_covers("fs:read", "fs"));
+    }
+
+    #[test]
+    fn unparseable_permissions_fall_back_to_exact_match() {
+        assert!(Permission::parse("exec").is_err());
+        assert!(rule_matches("exec", "exec"));
+        assert!(rule_matches("net:", "net:"));
+        assert!(!rule_matches("exec:git", "exec"));
+        assert!(rule_covers("net:", "net:"));
+    }
+
+    #[test]
+    fn delta_flags_broadened_scopes() {
+        let old = strings(&["fs:read:src/**", "net:api.github.com", "filesystem.read"]);
+        let new = strings(&["fs:read:src/lib/**", "net:*", "exec:git", "filesystem.read"]);
+        let delta = permission_delta(&old, &new);
+        assert_eq!(delta.broadened, ["net:*"]);
+        assert_eq!(delta.added, ["exec:git"]);
+        assert!(permission_delta(&new, &new).is_empty());
+        assert_eq!(
+            permission_delta(&old, &strings(&["fs:read:**"])).broadened,
+            ["fs:read:**"]
+        );
+    }
+}
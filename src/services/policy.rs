@@ -1,4 +1,29 @@
-use std::path::PathBuf;
+//! Policy loading and source matching.
+//!
+//! Policy comes from up to four layers, lowest first: `system`
+//! (`/etc/pater/policy.toml`, admin-managed), `user`
+//! (`~/.config/pater/policy.toml`), `project` (the nearest
+//! `.pater/policy.toml` up to the repo root) and `env` (the file named by
+//! `$PATER_POLICY`). Upper layers may only tighten what lower layers set:
+//! restrictive flags and deny lists accumulate, permissive flags and allow
+//! lists can only be narrowed. `[agent.<adapter>]` sections stack over their
+//! layer's `[general]` for that adapter only.
+//!
+//! Plugin list entries are `<name>` or `<name>@<marketplace>`, each part a
+//! glob (`experimental-*`); `blocked_permissions` and `allowed_permissions`
+//! entries are permission rules (see `services::permissions`).
+//! `permission_mode = "allowlist"` set by any layer blocks every permission
+//! `allowed_permissions` does not match.
+
+use crate::domain::models::{
+    EffectivePolicyField, ExpiredException, PermissionMode, PolicyException, PolicyFile,
+    PolicyGeneral, PolicyLayer, PolicyLayerGeneral, PolicyLayerInfo, PolicyShowReport,
+};
+use crate::services::glob::glob_match;
+use crate::services::permissions::{rule_covers, rule_matches, PermissionRule};
+use crate::services::storage::config_base_dir;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 pub fn canonical_market_source_id(raw: &str) -> String {
     let s = raw.trim();
@@ -51,9 +76,943 @@ pub fn source_matches_allowed(source: &str, allowed: &str) -> bool {
     canonical_market_source_id(source) == canonical_market_source_id(allowed)
 }
 
+fn split_plugin_entry(entry: &str) -> (&str, Option<&str>) {
+    match entry.rsplit_once('@') {
+        Some((name, market)) => (name, Some(market)),
+        None => (entry, None),
+    }
+}
+
+/// Whether a plugin list entry (`<name>` or `<name>@<marketplace>`, globs
+/// allowed) selects the plugin. Unqualified entries match every marketplace.
+fn plugin_entry_matches(entry: &str, name: &str, marketplace: &str) -> bool {
+    let (name_glob, market_glob) = split_plugin_entry(entry);
+    glob_match(name_glob, name) && market_glob.is_none_or(|m| glob_match(m, marketplace))
+}
+
+pub fn plugin_list_matches(list: &[String], name: &str, marketplace: &str) -> bool {
+    list.iter()
+        .any(|entry| plugin_entry_matches(entry, name, marketplace))
+}
+
+/// Whether `lower` allows everything `upper` names, treating `upper`'s
+/// patterns as literals. Used to narrow plugin allow lists across layers.
+fn plugin_entry_covers(upper: &str, lower: &str) -> bool {
+    let (upper_name, upper_market) = split_plugin_entry(upper);
+    let (lower_name, lower_market) = split_plugin_entry(lower);
+    glob_match(lower_name, upper_name)
+        && match (lower_market, upper_market) {
+            (None, _) => true,
+            (Some(l), Some(u)) => glob_match(l, u),
+            (Some(_), None) => false,
+        }
+}
+
+/// The plugin `permissions` the policy blocks, in the plugin's order.
+pub fn blocked_permissions(general: &PolicyGeneral, permissions: &[String]) -> Vec<String> {
+    permissions
+        .iter()
+        .filter(|perm| {
+            general
+                .blocked_permissions
+                .iter()
+                .any(|blocked| rule_matches(blocked, perm))
+                || (general.permission_mode == PermissionMode::Allowlist
+                    && !general
+                        .allowed_permissions
+                        .iter()
+                        .flatten()
+                        .any(|allowed| rule_matches(allowed, perm)))
+        })
+        .cloned()
+        .collect()
+}
+
+/// A policy file at one layer; `policy` is `None` when the file is absent.
+#[derive(Clone)]
+pub struct LoadedLayer {
+    pub name: String,
+    pub path: PathBuf,
+    pub policy: Option<PolicyLayer>,
+}
+
+/// `/etc/pater/policy.toml` (`%ProgramData%\pater` on Windows).
+/// `$PATER_SYSTEM_CONFIG_DIR` replaces the directory.
+fn system_policy_path() -> PathBuf {
+    let dir = match std::env::var_os("PATER_SYSTEM_CONFIG_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => std::env::var_os("ProgramData")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+            .join("pater"),
+        None => PathBuf::from("/etc/pater"),
+    };
+    dir.join("policy.toml")
+}
+
+/// Nearest `.pater/policy.toml` from the working dir, stopping at the repo
+/// root. Falls back to the working dir's path when there is none.
+fn project_policy_path() -> anyhow::Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    for dir in cwd.ancestors() {
+        let candidate = dir.join(".pater").join("policy.toml");
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    Ok(cwd.join(".pater").join("policy.toml"))
+}
+
+fn read_layer(name: &str, path: PathBuf) -> anyhow::Result<LoadedLayer> {
+    let policy =
+        if path.is_file() {
+            let raw = std::fs::read_to_string(&path)?;
+            Some(toml::from_str(&raw).map_err(|e| {
+                anyhow::anyhow!("cannot parse policy file {}: {}", path.display(), e)
+            })?)
+        } else {
+            None
+        };
+    let layer = LoadedLayer {
+        name: name.to_string(),
+        path,
+        policy,
+    };
+    check_exception_dates(&layer)?;
+    check_permission_rules(&layer)?;
+    Ok(layer)
+}
+
+/// Every policy layer, lowest first, whether or not its file exists.
+pub fn policy_layers() -> anyhow::Result<Vec<LoadedLayer>> {
+    let mut layers = vec![
+        read_layer("system", system_policy_path())?,
+        read_layer("user", config_base_dir()?.join("policy.toml"))?,
+        read_layer("project", project_policy_path()?)?,
+    ];
+    if let Some(path) = std::env::var_os("PATER_POLICY") {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            anyhow::bail!("PATER_POLICY names a missing file: {}", path.display());
+        }
+        layers.push(read_layer("env", path)?);
+    }
+    Ok(layers)
+}
+
+pub fn load_policy() -> anyhow::Result<PolicyFile> {
+    Ok(merge_layers(&policy_layers()?).0)
+}
+
+/// A single policy file merged on its own, for trying out a candidate
+/// policy without installing it as a layer.
+pub fn load_policy_file(path: &Path) -> anyhow::Result<PolicyFile> {
+    if !path.is_file() {
+        anyhow::bail!("policy file does not exist: {}", path.display());
+    }
+    Ok(merge_layers(&[read_layer("candidate", path.to_path_buf())?]).0)
+}
+
+/// A merged field and the layers that decided it.
+struct Merged<T> {
+    value: T,
+    origin: Vec<String>,
+}
+
+fn set_values<'a, T>(
+    layers: &'a [LoadedLayer],
+    get: impl Fn(&'a PolicyLayerGeneral) -> Option<T>,
+) -> Vec<(&'a str, T)> {
+    layers
+        .iter()
+        .filter_map(|l| Some((l.name.as_str(), get(&l.policy.as_ref()?.general)?)))
+        .collect()
+}
+
+fn names<T>(set: &[(&str, T)], keep: impl Fn(&T) -> bool) -> Vec<String> {
+    set.iter()
+        .filter(|(_, v)| keep(v))
+        .map(|(n, _)| n.to_string())
+        .collect()
+}
+
+/// A flag that restricts when true: any layer can turn it on, none off.
+fn restrictive_flag(
+    layers: &[LoadedLayer],
+    get: impl Fn(&PolicyLayerGeneral) -> Option<bool>,
+) -> Merged<bool> {
+    let set = set_values(layers, get);
+    let value = set.iter().any(|(_, v)| *v);
+    Merged {
+        value,
+        origin: names(&set, |v| *v == value),
+    }
+}
+
+/// Layers that may grant something no layer below them grants: the
+/// `[general]` section of system, user, env and candidate files. A project
+/// checkout or an `[agent.<name>]` section can only narrow a grant.
+fn may_grant(layer: &str) -> bool {
+    layer != "project" && !layer.contains(":agent.")
+}
+
+/// A flag that loosens when true: it holds only if a layer that `may_grant`
+/// turns it on and no layer sets it false.
+fn permissive_flag(
+    layers: &[LoadedLayer],
+    get: impl Fn(&PolicyLayerGeneral) -> Option<bool>,
+) -> Merged<bool> {
+    let set = set_values(layers, get);
+    let value = set.iter().any(|(n, v)| *v && may_grant(n)) && set.iter().all(|(_, v)| *v);
+    Merged {
+        value,
+        origin: names(&set, |v| *v == value),
+    }
+}
+
+impl<T> Merged<T> {
+    fn map<U>(self, f: impl FnOnce(T) -> U) -> Merged<U> {
+        Merged {
+            value: f(self.value),
+            origin: self.origin,
+        }
+    }
+}
+
+/// A deny list: entries from every layer apply.
+fn deny_list<'a>(
+    layers: &'a [LoadedLayer],
+    get: impl Fn(&'a PolicyLayerGeneral) -> Option<&'a Vec<String>>,
+) -> Merged<Vec<String>> {
+    let set = set_values(layers, get);
+    let mut value: Vec<String> = Vec::new();
+    for (_, list) in &set {
+        for item in *list {
+            if !value.contains(item) {
+                value.push(item.clone());
+            }
+        }
+    }
+    Merged {
+        value,
+        origin: names(&set, |list| !list.is_empty()),
+    }
+}
+
+/// An allow list: each layer that sets one, even to `[]`, narrows the one
+/// below, keeping only entries `covered` by it. `None` means no layer set
+/// one. Only layers accepted by `opens` may set a non-empty first list;
+/// later lists from other layers are ignored until one does.
+fn allow_list<'a, L: AsRef<[String]>>(
+    layers: &'a [LoadedLayer],
+    get: impl Fn(&'a PolicyLayerGeneral) -> Option<L>,
+    covered: impl Fn(&str, &str) -> bool,
+    opens: impl Fn(&str) -> bool,
+) -> Merged<Option<Vec<String>>> {
+    let mut value: Option<Vec<String>> = None;
+    let mut origin = Vec::new();
+    for (name, list) in set_values(layers, get) {
+        value = Some(match value {
+            None if !opens(name) && !list.as_ref().is_empty() => continue,
+            None => list.as_ref().to_vec(),
+            Some(lower) => list
+                .as_ref()
+                .iter()
+                .filter(|item| lower.iter().any(|l| covered(item, l)))
+                .cloned()
+                .collect(),
+        });
+        origin.push(name.to_string());
+    }
+    Merged { value, origin }
+}
+
+/// `allowed_permissions`. A layer that turns on allowlist mode before any
+/// list is set allows nothing, so it locks an empty list like `[]` would.
+fn allowed_permissions(layers: &[LoadedLayer]) -> Merged<Option<Vec<String>>> {
+    let mut layers = layers.to_vec();
+    let first = layers
+        .iter_mut()
+        .filter_map(|l| l.policy.as_mut())
+        .map(|p| &mut p.general)
+        .find(|g| {
+            g.allowed_permissions.is_some() || g.permission_mode == Some(PermissionMode::Allowlist)
+        });
+    if let Some(g) = first {
+        g.allowed_permissions.get_or_insert_with(Vec::new);
+    }
+    allow_list(
+        &layers,
+        |g| g.allowed_permissions.as_ref(),
+        |upper, lower| rule_covers(lower, upper),
+        may_grant,
+    )
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    era * 146_097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719_468
+}
+
+/// `YYYY-MM-DD` as days since the epoch.
+fn parse_day(raw: &str) -> Option<i64> {
+    let mut parts = raw.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    ((1..=12).contains(&month) && (1..=31).contains(&day))
+        .then(|| days_from_civil(year, month, day))
+}
+
+fn today() -> i64 {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    (secs / 86_400) as i64
+}
+
+/// An exception applies through its `expires` day.
+fn exception_expired(e: &PolicyException, today: i64) -> bool {
+    e.expires()
+        .and_then(parse_day)
+        .is_some_and(|day| day < today)
+}
+
+fn active_exceptions(list: Option<&Vec<PolicyException>>, today: i64) -> Option<Vec<String>> {
+    list.map(|list| {
+        list.iter()
+            .filter(|e| !exception_expired(e, today))
+            .map(|e| e.plugin().to_string())
+            .collect()
+    })
+}
+
+/// Each section of a layer with the name origins use for it: `[general]`
+/// as the layer name, `[agent.<a>]` as `<layer>:agent.<a>`.
+fn layer_sections(layer: &LoadedLayer) -> Vec<(String, &PolicyLayerGeneral)> {
+    let Some(policy) = &layer.policy else {
+        return Vec::new();
+    };
+    std::iter::once((layer.name.clone(), &policy.general))
+        .chain(
+            policy
+                .agent
+                .iter()
+                .map(|(agent, g)| (format!("{}:agent.{}", layer.name, agent), g)),
+        )
+        .collect()
+}
+
+fn exception_lists(g: &PolicyLayerGeneral) -> [(&'static str, &Option<Vec<PolicyException>>); 2] {
+    [
+        (
+            "allow_unknown_license_plugins",
+            &g.allow_unknown_license_plugins,
+        ),
+        (
+            "allow_external_reference_plugins",
+            &g.allow_external_reference_plugins,
+        ),
+    ]
+}
+
+fn expired_exceptions(layers: &[LoadedLayer], today: i64) -> Vec<ExpiredException> {
+    let mut out = Vec::new();
+    for l in layers {
+        for (layer, g) in layer_sections(l) {
+            for (field, list) in exception_lists(g) {
+                for e in list.iter().flatten() {
+                    let PolicyException::Detailed {
+                        plugin,
+                        reason,
+                        approved_by,
+                        expires: Some(expires),
+                    } = e
+                    else {
+                        continue;
+                    };
+                    if exception_expired(e, today) {
+                        out.push(ExpiredException {
+                            layer: layer.clone(),
+                            field: field.to_string(),
+                            plugin: plugin.clone(),
+                            expires: expires.clone(),
+                            reason: reason.clone(),
+                            approved_by: approved_by.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Rejects exceptions whose `expires` is not a date, so a typo cannot make
+/// an exception permanent.
+fn check_exception_dates(layer: &LoadedLayer) -> anyhow::Result<()> {
+    for (section, g) in layer_sections(layer) {
+        for (field, list) in exception_lists(g) {
+            for e in list.iter().flatten() {
+                if let Some(expires) = e.expires().filter(|d| parse_day(d).is_none()) {
+                    anyhow::bail!(
+                        "invalid expires date {:?} for {} in {} policy ({}): expected YYYY-MM-DD",
+                        expires,
+                        e.plugin(),
+                        section,
+                        field
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rejects permission rules outside the vocabulary, which could otherwise
+/// never match the permission they were meant to block or allow.
+fn check_permission_rules(layer: &LoadedLayer) -> anyhow::Result<()> {
+    for (section, g) in layer_sections(layer) {
+        for (field, list) in [
+            ("blocked_permissions", &g.blocked_permissions),
+            ("allowed_permissions", &g.allowed_permissions),
+        ] {
+            for rule in list.iter().flatten() {
+                if let Err(e) = PermissionRule::parse(rule) {
+                    anyhow::bail!("{} in {} policy ({})", e, section, field);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The layers as `agent` sees them: each layer's `[agent.<agent>]` section
+/// stacks right above its `[general]`, so it can only tighten.
+fn agent_layers(layers: &[LoadedLayer], agent: &str) -> Vec<LoadedLayer> {
+    let mut out = Vec::new();
+    for l in layers {
+        out.push(l.clone());
+        if let Some(section) = l.policy.as_ref().and_then(|p| p.agent.get(agent)) {
+            out.push(LoadedLayer {
+                name: format!("{}:agent.{}", l.name, agent),
+                path: l.path.clone(),
+                policy: Some(PolicyLayer {
+                    general: section.clone(),
+                    ..PolicyLayer::default()
+                }),
+            });
+        }
+    }
+    out
+}
+
+/// Merges `layers` (lowest first) into the effective policy, with the
+/// origin of every field for `policy show --effective`. Fields an
+/// `[agent.<name>]` section decides are listed as `agent.<name>.<field>`.
+pub fn merge_layers(layers: &[LoadedLayer]) -> (PolicyFile, Vec<EffectivePolicyField>) {
+    let today = today();
+    let (general, mut fields) = merge_general(layers, today);
+    let mut agents = BTreeMap::new();
+    for l in layers {
+        for agent in l.policy.iter().flat_map(|p| p.agent.keys()) {
+            if agents.contains_key(agent) {
+                continue;
+            }
+            let (merged, agent_fields) = merge_general(&agent_layers(layers, agent), today);
+            let section = format!(":agent.{}", agent);
+            fields.extend(
+                agent_fields
+                    .into_iter()
+                    .filter(|f| f.origin.iter().any(|o| o.ends_with(&section)))
+                    .map(|f| EffectivePolicyField {
+                        field: format!("agent.{}.{}", agent, f.field),
+                        ..f
+                    }),
+            );
+            agents.insert(agent.clone(), merged);
+        }
+    }
+    let policy = PolicyFile {
+        general,
+        agents,
+        expired_exceptions: expired_exceptions(layers, today),
+    };
+    (policy, fields)
+}
+
+fn merge_general(layers: &[LoadedLayer], today: i64) -> (PolicyGeneral, Vec<EffectivePolicyField>) {
+    let mut fields = Vec::new();
+    let mut record = |field: &str, value: serde_json::Value, origin: Vec<String>| {
+        fields.push(EffectivePolicyField {
+            field: field.to_string(),
+            value,
+            origin,
+        });
+    };
+    macro_rules! take {
+        ($field:literal, $merged:expr) => {{
+            let merged = $merged;
+            record($field, serde_json::json!(merged.value), merged.origin);
+            merged.value
+        }};
+    }
+    let general = PolicyGeneral {
+        require_signed_marketplace: take!(
+            "require_signed_marketplace",
+            restrictive_flag(layers, |g| g.require_signed_marketplace)
+        ),
+        allowed_sources: take!(
+            "allowed_sources",
+            allow_list(
+                layers,
+                // `[]` has always meant "any source" here. With no list every
+                // source is allowed, so even the first list only narrows.
+                |g| g.allowed_sources.as_ref().filter(|l| !l.is_empty()),
+                source_matches_allowed,
+                |_| true
+            )
+        ),
+        denied_plugins: take!(
+            "denied_plugins",
+            deny_list(layers, |g| g.denied_plugins.as_ref())
+        ),
+        blocked_permissions: take!(
+            "blocked_permissions",
+            deny_list(layers, |g| g.blocked_permissions.as_ref())
+        ),
+        permission_mode: take!(
+            "permission_mode",
+            restrictive_flag(layers, |g| g
+                .permission_mode
+                .map(|m| m == PermissionMode::Allowlist))
+            .map(|allowlist| if allowlist {
+                PermissionMode::Allowlist
+            } else {
+                PermissionMode::Denylist
+            })
+        ),
+        allowed_permissions: take!("allowed_permissions", allowed_permissions(layers)),
+        block_unknown_licenses: take!(
+            "block_unknown_licenses",
+            restrictive_flag(layers, |g| g.block_unknown_licenses)
+        ),
+        allow_unknown_license_plugins: take!(
+            "allow_unknown_license_plugins",
+            allow_list(
+                layers,
+                |g| active_exceptions(g.allow_unknown_license_plugins.as_ref(), today),
+                plugin_entry_covers,
+                may_grant
+            )
+        )
+        .unwrap_or_default(),
+        allow_external_reference_installs: take!(
+            "allow_external_reference_installs",
+            permissive_flag(layers, |g| g.allow_external_reference_installs)
+        ),
+        allow_external_reference_plugins: take!(
+            "allow_external_reference_plugins",
+            allow_list(
+                layers,
+                |g| active_exceptions(g.allow_external_reference_plugins.as_ref(), today),
+                plugin_entry_covers,
+                may_grant
+            )
+        )
+        .unwrap_or_default(),
+    };
+    (general, fields)
+}
+
+pub fn policy_show(effective: bool) -> anyhow::Result<PolicyShowReport> {
+    let layers = policy_layers()?;
+    let infos = layers
+        .iter()
+        .map(|l| {
+            Ok(PolicyLayerInfo {
+                name: l.name.clone(),
+                path: l.path.to_string_lossy().to_string(),
+                loaded: l.policy.is_some(),
+                settings: serde_json::to_value(&l.policy)?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(PolicyShowReport {
+        layers: infos,
+        effective: effective.then(|| merge_layers(&layers).1),
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{canonical_market_source_id, source_matches_allowed};
+    use super::{
+        blocked_permissions, canonical_market_source_id, merge_layers, parse_day,
+        plugin_entry_matches, source_matches_allowed, LoadedLayer,
+    };
+    use std::path::PathBuf;
+
+    fn layer(name: &str, toml: &str) -> LoadedLayer {
+        LoadedLayer {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            policy: Some(toml::from_str(toml).expect("layer toml")),
+        }
+    }
+
+    fn origin_of(
+        fields: &[crate::domain::models::EffectivePolicyField],
+        field: &str,
+    ) -> Vec<String> {
+        fields
+            .iter()
+            .find(|f| f.field == field)
+            .map(|f| f.origin.clone())
+            .expect("field")
+    }
+
+    #[test]
+    fn upper_layers_cannot_loosen_flags() {
+        let layers = [
+            layer(
+                "system",
+                "[general]\nrequire_signed_marketplace = true\nallow_external_reference_installs = false",
+            ),
+            layer(
+                "user",
+                "[general]\nrequire_signed_marketplace = false\nallow_external_reference_installs = true",
+            ),
+            layer("project", "[general]\nblock_unknown_licenses = true"),
+        ];
+        let (policy, fields) = merge_layers(&layers);
+        assert!(policy.general.require_signed_marketplace);
+        assert!(!policy.general.allow_external_reference_installs);
+        assert!(policy.general.block_unknown_licenses);
+        assert_eq!(origin_of(&fields, "require_signed_marketplace"), ["system"]);
+        assert_eq!(
+            origin_of(&fields, "allow_external_reference_installs"),
+            ["system"]
+        );
+        assert_eq!(origin_of(&fields, "block_unknown_licenses"), ["project"]);
+
+        let (policy, fields) = merge_layers(&[layer(
+            "user",
+            "[general]\nallow_external_reference_installs = true",
+        )]);
+        assert!(policy.general.allow_external_reference_installs);
+        assert!(origin_of(&fields, "denied_plugins").is_empty());
+    }
+
+    #[test]
+    fn plugin_entries_take_globs_and_marketplace_qualifiers() {
+        assert!(plugin_entry_matches(
+            "experimental-*",
+            "experimental-lsp",
+            "rack"
+        ));
+        assert!(!plugin_entry_matches("experimental-*", "stable", "rack"));
+        assert!(plugin_entry_matches("lint@acme", "lint", "acme"));
+        assert!(!plugin_entry_matches("lint@acme", "lint", "rack"));
+        assert!(plugin_entry_matches(
+            "*@community-*",
+            "any",
+            "community-main"
+        ));
+        assert!(plugin_entry_matches("lint", "lint", "anything"));
+    }
+
+    #[test]
+    fn plugin_allow_lists_narrow_by_pattern() {
+        let layers = [
+            layer(
+                "system",
+                "[general]\nallow_unknown_license_plugins = [\"internal-*@acme\"]",
+            ),
+            layer(
+                "user",
+                "[general]\nallow_unknown_license_plugins = [\"internal-lint@acme\", \"internal-lint\", \"other\"]",
+            ),
+        ];
+        let (policy, _) = merge_layers(&layers);
+        assert_eq!(
+            policy.general.allow_unknown_license_plugins,
+            ["internal-lint@acme"]
+        );
+    }
+
+    #[test]
+    fn grants_only_narrow_and_empty_lists_lock() {
+        let locked = [
+            layer(
+                "system",
+                "[general]\nallow_unknown_license_plugins = []\nallow_external_reference_installs = false",
+            ),
+            layer(
+                "user",
+                "[general]\nallow_unknown_license_plugins = [\"*\"]\nallow_external_reference_installs = true",
+            ),
+        ];
+        let (policy, fields) = merge_layers(&locked);
+        assert!(policy.general.allow_unknown_license_plugins.is_empty());
+        assert!(!policy.general.allow_external_reference_installs);
+        assert_eq!(
+            origin_of(&fields, "allow_unknown_license_plugins"),
+            ["system", "user"]
+        );
+
+        // A project checkout cannot grant what no lower layer grants.
+        let project_only = [layer(
+            "project",
+            "[general]\nallow_unknown_license_plugins = [\"*\"]\nallow_external_reference_installs = true\nallow_external_reference_plugins = [\"x\"]",
+        )];
+        let (policy, fields) = merge_layers(&project_only);
+        assert!(policy.general.allow_unknown_license_plugins.is_empty());
+        assert!(policy.general.allow_external_reference_plugins.is_empty());
+        assert!(!policy.general.allow_external_reference_installs);
+        assert!(origin_of(&fields, "allow_unknown_license_plugins").is_empty());
+
+        // ...but it can narrow a grant below it.
+        let narrowed = [
+            layer(
+                "user",
+                "[general]\nallow_unknown_license_plugins = [\"a\", \"b\"]\nallow_external_reference_installs = true",
+            ),
+            layer(
+                "project",
+                "[general]\nallow_unknown_license_plugins = [\"a\"]\nallow_external_reference_installs = true",
+            ),
+        ];
+        let (policy, _) = merge_layers(&narrowed);
+        assert_eq!(policy.general.allow_unknown_license_plugins, ["a"]);
+        assert!(policy.general.allow_external_reference_installs);
+
+        // An agent section cannot open a grant either.
+        let (policy, _) = merge_layers(&[layer(
+            "user",
+            "[agent.codex]\nallow_unknown_license_plugins = [\"*\"]",
+        )]);
+        assert!(policy
+            .for_agent("codex")
+            .allow_unknown_license_plugins
+            .is_empty());
+
+        // Allowed permissions are a grant too.
+        let (policy, _) = merge_layers(&[
+            layer("system", "[general]\npermission_mode = \"allowlist\""),
+            layer("user", "[agent.codex]\nallowed_permissions = [\"exec:*\"]"),
+            layer("project", "[general]\nallowed_permissions = [\"exec:*\"]"),
+        ]);
+        let exec = ["exec:git".to_string()];
+        assert_eq!(blocked_permissions(&policy.general, &exec), exec);
+        assert_eq!(blocked_permissions(policy.for_agent("codex"), &exec), exec);
+
+        // Allowed sources only ever narrow "any source", so a project may set them.
+        let (policy, _) = merge_layers(&[layer(
+            "project",
+            "[general]\nallowed_sources = [\"org/rack\"]",
+        )]);
+        assert_eq!(
+            policy.general.allowed_sources.as_deref(),
+            Some(&["org/rack".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn expired_exceptions_keep_their_list_locked() {
+        let layers = [
+            layer(
+                "system",
+                "[general]\nallow_unknown_license_plugins = [{ plugin = \"old\", expires = 2000-01-01 }]",
+            ),
+            layer(
+                "user",
+                "[general]\nallow_unknown_license_plugins = [\"*\"]",
+            ),
+        ];
+        let (policy, _) = merge_layers(&layers);
+        assert!(policy.general.allow_unknown_license_plugins.is_empty());
+    }
+
+    #[test]
+    fn deny_lists_accumulate_and_allow_lists_narrow() {
+        let layers = [
+            layer(
+                "system",
+                "[general]\ndenied_plugins = [\"a\"]\nallowed_sources = [\"org/rack\", \"org/extra\"]",
+            ),
+            layer(
+                "user",
+                "[general]\ndenied_plugins = [\"b\", \"a\"]\nallowed_sources = [\"https://github.com/org/rack\", \"evil/rack\"]",
+            ),
+            layer("project", "[general]\nallowed_sources = []"),
+        ];
+        let (policy, fields) = merge_layers(&layers);
+        assert_eq!(policy.general.denied_plugins, ["a", "b"]);
+        assert_eq!(
+            policy.general.allowed_sources.as_deref(),
+            Some(&["https://github.com/org/rack".to_string()][..])
+        );
+        assert_eq!(origin_of(&fields, "allowed_sources"), ["system", "user"]);
+
+        let disjoint = [
+            layer("system", "[general]\nallowed_sources = [\"org/rack\"]"),
+            layer("user", "[general]\nallowed_sources = [\"evil/rack\"]"),
+        ];
+        let (policy, _) = merge_layers(&disjoint);
+        assert_eq!(policy.general.allowed_sources, Some(Vec::new()));
+        assert!(merge_layers(&[]).0.general.allowed_sources.is_none());
+    }
+
+    #[test]
+    fn allowlist_mode_blocks_unlisted_permissions() {
+        let perms = ["fs.read".to_string(), "net.fetch".to_string()];
+        let layers = [
+            layer(
+                "system",
+                "[general]\npermission_mode = \"allowlist\"\nallowed_permissions = [\"fs.*\", \"net.*\"]",
+            ),
+            layer(
+                "user",
+                "[general]\npermission_mode = \"denylist\"\nallowed_permissions = [\"fs.read\", \"exec.*\"]",
+            ),
+        ];
+        let (policy, _) = merge_layers(&layers);
+        assert_eq!(
+            policy.general.allowed_permissions.as_deref(),
+            Some(&["fs.read".to_string()][..])
+        );
+        assert_eq!(blocked_permissions(&policy.general, &perms), ["net.fetch"]);
+
+        let (denylist, _) = merge_layers(&[layer(
+            "user",
+            "[general]\nblocked_permissions = [\"net.*\"]",
+        )]);
+        assert_eq!(
+            blocked_permissions(&denylist.general, &perms),
+            ["net.fetch"]
+        );
+        let (empty, _) =
+            merge_layers(&[layer("user", "[general]\npermission_mode = \"allowlist\"")]);
+        assert_eq!(blocked_permissions(&empty.general, &perms), perms);
+    }
+
+    #[test]
+    fn empty_system_allowlist_cannot_be_widened() {
+        let layers = [
+            layer(
+                "system",
+                "[general]\npermission_mode = \"allowlist\"\nallowed_permissions = []",
+            ),
+            layer("user", "[general]\nallowed_permissions = [\"exec:*\"]"),
+        ];
+        let (policy, fields) = merge_layers(&layers);
+        assert_eq!(policy.general.allowed_permissions, Some(Vec::new()));
+        assert_eq!(
+            origin_of(&fields, "allowed_permissions"),
+            ["system", "user"]
+        );
+        assert_eq!(
+            blocked_permissions(&policy.general, &["exec:git".to_string()]),
+            ["exec:git"]
+        );
+    }
+
+    #[test]
+    fn allowlist_mode_without_a_list_cannot_be_widened() {
+        let exec = ["exec:git".to_string()];
+        let layers = [
+            layer("system", "[general]\npermission_mode = \"allowlist\""),
+            layer("user", "[general]\nallowed_permissions = [\"exec:*\"]"),
+        ];
+        let (policy, fields) = merge_layers(&layers);
+        assert_eq!(policy.general.allowed_permissions, Some(Vec::new()));
+        assert_eq!(
+            origin_of(&fields, "allowed_permissions"),
+            ["system", "user"]
+        );
+        assert_eq!(blocked_permissions(&policy.general, &exec), exec);
+
+        // Neither a project checkout nor an agent section reopens it.
+        let layers = [
+            layer(
+                "system",
+                "[general]\npermission_mode = \"allowlist\"\n[agent.codex]\nallowed_permissions = [\"exec:*\"]",
+            ),
+            layer("project", "[general]\nallowed_permissions = [\"exec:*\"]"),
+        ];
+        let (policy, _) = merge_layers(&layers);
+        assert_eq!(blocked_permissions(&policy.general, &exec), exec);
+        assert_eq!(blocked_permissions(policy.for_agent("codex"), &exec), exec);
+
+        // A list below still decides; turning the mode on again does not empty it.
+        let layers = [
+            layer(
+                "system",
+                "[general]\npermission_mode = \"allowlist\"\nallowed_permissions = [\"exec:*\"]",
+            ),
+            layer("user", "[general]\npermission_mode = \"allowlist\""),
+        ];
+        let (policy, _) = merge_layers(&layers);
+        assert!(blocked_permissions(&policy.general, &exec).is_empty());
+    }
+
+    #[test]
+    fn agent_sections_tighten_general_for_that_agent_only() {
+        let layers = [
+            layer(
+                "system",
+                "[general]\nblocked_permissions = [\"secrets\"]\n[agent.codex]\nblocked_permissions = [\"exec\"]",
+            ),
+            layer(
+                "user",
+                "[agent.codex]\nrequire_signed_marketplace = false\n[agent.claude]\ndenied_plugins = [\"x\"]",
+            ),
+        ];
+        let (policy, fields) = merge_layers(&layers);
+        assert_eq!(
+            policy.for_agent("codex").blocked_permissions,
+            ["secrets", "exec"]
+        );
+        assert_eq!(policy.for_agent("claude").denied_plugins, ["x"]);
+        assert_eq!(policy.for_agent("all").blocked_permissions, ["secrets"]);
+        assert!(policy.general.denied_plugins.is_empty());
+        assert_eq!(
+            origin_of(&fields, "agent.codex.blocked_permissions"),
+            ["system", "system:agent.codex"]
+        );
+        // Fields no agent section touches are not repeated per agent.
+        assert!(!fields
+            .iter()
+            .any(|f| f.field == "agent.claude.blocked_permissions"));
+    }
+
+    #[test]
+    fn exception_dates_expire_after_their_day() {
+        assert_eq!(parse_day("1970-01-01"), Some(0));
+        assert_eq!(parse_day("2000-03-01"), Some(11_017));
+        assert_eq!(parse_day("2024-02-29"), Some(19_782));
+        assert_eq!(parse_day("2024-13-01"), None);
+        assert_eq!(parse_day("soon"), None);
+
+        let layers = [layer(
+            "user",
+            "[general]\nallow_unknown_license_plugins = [\"plain\", { plugin = \"old\", expires = 2000-01-01 }, { plugin = \"new\", expires = \"2999-01-01\" }]",
+        )];
+        let (policy, _) = merge_layers(&layers);
+        assert_eq!(
+            policy.general.allow_unknown_license_plugins,
+            ["plain", "new"]
+        );
+        assert_eq!(policy.expired_exceptions.len(), 1);
+        assert_eq!(policy.expired_exceptions[0].plugin, "old");
+        assert_eq!(policy.expired_exceptions[0].expires, "2000-01-01");
+    }
 
     #[test]
     fn source_matching_normalizes_github_forms() {
@@ -1,18 +1,22 @@
-use crate::domain::models::{DoctorReport, ReleaseCheckReport, TrustStatus};
+use crate::domain::models::{DoctorReport, ExpiredException, ReleaseCheckReport, TrustStatus};
 
 pub fn build_release_check_report(
     trust: TrustStatus,
     doctor: DoctorReport,
     rack_license_audit: String,
+    expired_policy_exceptions: Vec<ExpiredException>,
 ) -> ReleaseCheckReport {
     let rack_audit_ok = rack_license_audit == "ok" || rack_license_audit == "not_applicable";
-    let overall =
-        if trust.default_marketplace_signature_ok && doctor.overall == "ok" && rack_audit_ok {
-            "ok"
-        } else {
-            "needs_attention"
-        }
-        .to_string();
+    let overall = if trust.default_marketplace_signature_ok
+        && doctor.overall == "ok"
+        && rack_audit_ok
+        && expired_policy_exceptions.is_empty()
+    {
+        "ok"
+    } else {
+        "needs_attention"
+    }
+    .to_string();
 
     let mut recommendations = Vec::new();
     if !trust.default_marketplace_signature_ok {
@@ -24,12 +28,16 @@ pub fn build_release_check_report(
     if rack_license_audit == "failed" || rack_license_audit == "error" {
         recommendations.push("Run `pater rack license-audit --rack-dir <rack-dir>` and resolve unknown/proprietary plugins before release.".to_string());
     }
+    if !expired_policy_exceptions.is_empty() {
+        recommendations.push("Renew or remove expired policy exceptions; they no longer apply (see `pater policy show`).".to_string());
+    }
 
     ReleaseCheckReport {
         overall,
         trust,
         doctor,
         rack_license_audit,
+        expired_policy_exceptions,
         recommendations,
     }
 }
@@ -1,12 +1,33 @@
+use crate::domain::constants::LOCKFILE_SCHEMA_VERSION;
 use crate::domain::models::{InstalledPlugin, Lockfile, State};
+use crate::services::migrations::{check_existing, load_migrated, LOCKFILE_SCHEMA, STATE_SCHEMA};
+use crate::services::store::{import_tree, link_view};
+use std::ffi::OsString;
 use std::path::PathBuf;
 
+/// The user's home dir: `$HOME`, else `%USERPROFILE%`, else
+/// `%HOMEDRIVE%%HOMEPATH%`, so Windows shells without `HOME` work.
+pub fn home_dir() -> anyhow::Result<PathBuf> {
+    home_from(|key| std::env::var_os(key)).ok_or_else(|| {
+        anyhow::anyhow!("cannot determine home directory: set HOME (or USERPROFILE on Windows)")
+    })
+}
+
+fn home_from(var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let set = |key: &str| var(key).filter(|v| !v.is_empty());
+    if let Some(home) = set("HOME").or_else(|| set("USERPROFILE")) {
+        return Some(PathBuf::from(home));
+    }
+    let mut home = set("HOMEDRIVE")?;
+    home.push(set("HOMEPATH")?);
+    Some(PathBuf::from(home))
+}
+
 pub fn audit(action: &str, data: serde_json::Value) {
-    let home = match std::env::var("HOME") {
-        Ok(h) => h,
-        Err(_) => return,
+    let Ok(home) = home_dir() else {
+        return;
     };
-    let path = PathBuf::from(home).join(".config/pater/audit.jsonl");
+    let path = home.join(".config/pater/audit.jsonl");
     if let Some(parent) = path.parent() {
         let _ = std::fs::create_dir_all(parent);
     }
@@ -44,13 +65,24 @@ pub fn upsert_installed(state: &mut State, entry: InstalledPlugin) {
     }
 }
 
+/// Everything pater generates: runtime, store and config snapshots.
+pub fn data_base_dir() -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".local").join("share").join("pater"))
+}
+
+/// User config, state, lockfile, trust keys and audit log.
+pub fn config_base_dir() -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".config").join("pater"))
+}
+
 pub fn runtime_base_dir() -> anyhow::Result<PathBuf> {
-    let home = std::env::var("HOME")?;
-    Ok(PathBuf::from(home)
-        .join(".local")
-        .join("share")
-        .join("pater")
-        .join("runtime"))
+    Ok(data_base_dir()?.join("runtime"))
+}
+
+/// Content-addressed plugin trees, keyed by tree hash. Runtime dirs are
+/// hardlink views over these entries.
+pub fn store_base_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_base_dir()?.join("store"))
 }
 
 pub fn runtime_plugins_dir() -> anyhow::Result<PathBuf> {
@@ -65,54 +97,76 @@ pub fn runtime_bridges_dir() -> anyhow::Result<PathBuf> {
     Ok(runtime_base_dir()?.join("bridges"))
 }
 
-pub fn materialize_plugin(name: &str, source_path: &std::path::Path) -> anyhow::Result<PathBuf> {
-    let base = runtime_plugins_dir()?;
-    std::fs::create_dir_all(&base)?;
-    let dst = base.join(name);
-    copy_dir_all(source_path, &dst)?;
-    Ok(dst)
+fn path_segment(raw: &str) -> String {
+    let cleaned = raw.replace(['/', '\\'], "_");
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        format!("_{}", cleaned)
+    } else {
+        cleaned
+    }
 }
 
-fn copy_dir_all(src: &std::path::Path, dst: &std::path::Path) -> anyhow::Result<()> {
-    if dst.exists() {
-        std::fs::remove_dir_all(dst)?;
-    }
-    std::fs::create_dir_all(dst)?;
-    for entry in std::fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        let to = dst.join(entry.file_name());
-        if ty.is_dir() {
-            copy_dir_all(&entry.path(), &to)?;
-        } else {
-            std::fs::copy(entry.path(), to)?;
-        }
+/// Runtime dirs are namespaced by marketplace so same-named plugins from
+/// different marketplaces never share files.
+pub fn runtime_marketplace_dir(marketplace: &str) -> anyhow::Result<PathBuf> {
+    Ok(runtime_plugins_dir()?.join(path_segment(marketplace)))
+}
+
+pub fn runtime_plugin_dir(marketplace: &str, name: &str) -> anyhow::Result<PathBuf> {
+    Ok(runtime_marketplace_dir(marketplace)?.join(path_segment(name)))
+}
+
+/// Imports `source_path` into the content-addressed store and exposes it at
+/// the plugin's runtime dir. Returns the runtime dir and the store hash.
+/// Every install scope shares this one user-level view.
+pub fn materialize_plugin(
+    marketplace: &str,
+    name: &str,
+    source_path: &std::path::Path,
+) -> anyhow::Result<(PathBuf, String)> {
+    let dst = runtime_plugin_dir(marketplace, name)?;
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)?;
     }
-    std::fs::write(dst.join(".pater-managed"), "managed-by=pater\n")?;
-    Ok(())
+    let (hash, _entry) = import_tree(source_path)?;
+    link_view(&hash, &dst)?;
+    Ok((dst, hash))
 }
 
-fn state_path() -> anyhow::Result<PathBuf> {
-    let home = std::env::var("HOME")?;
-    Ok(PathBuf::from(home).join(".config/pater/state.json"))
+/// Pre-pater snapshots of agent config files, one per adapter.
+pub fn adapter_originals_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_base_dir()?.join("adapter-originals"))
 }
 
-fn lockfile_path() -> anyhow::Result<PathBuf> {
-    let home = std::env::var("HOME")?;
-    Ok(PathBuf::from(home).join(".config/pater/pater.lock"))
+/// Copies of agent files taken before each adapter write, one dir per run.
+pub fn adapter_backups_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_base_dir()?.join("backups"))
+}
+
+/// User-defined declarative adapters (`*.toml`).
+pub fn adapter_specs_dir() -> anyhow::Result<PathBuf> {
+    Ok(config_base_dir()?.join("adapters"))
+}
+
+pub fn state_path() -> anyhow::Result<PathBuf> {
+    Ok(config_base_dir()?.join("state.json"))
+}
+
+pub fn lockfile_path() -> anyhow::Result<PathBuf> {
+    Ok(config_base_dir()?.join("pater.lock"))
 }
 
 pub fn load_state() -> anyhow::Result<State> {
     let p = state_path()?;
-    if !p.exists() {
-        return Ok(State::default());
+    match load_migrated(&p, &STATE_SCHEMA)? {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(State::default()),
     }
-    let raw = std::fs::read_to_string(p)?;
-    Ok(serde_json::from_str(&raw)?)
 }
 
 pub fn save_state(s: &State) -> anyhow::Result<()> {
     let p = state_path()?;
+    check_existing(&p, &STATE_SCHEMA)?;
     if let Some(parent) = p.parent() {
         std::fs::create_dir_all(parent)?;
     }
@@ -122,13 +176,43 @@ pub fn save_state(s: &State) -> anyhow::Result<()> {
 
 pub fn save_lockfile(state: &State) -> anyhow::Result<()> {
     let lock = Lockfile {
-        version: 1,
+        version: LOCKFILE_SCHEMA_VERSION,
         plugins: state.installed.clone(),
     };
     let p = lockfile_path()?;
+    check_existing(&p, &LOCKFILE_SCHEMA)?;
     if let Some(parent) = p.parent() {
         std::fs::create_dir_all(parent)?;
     }
     std::fs::write(p, serde_json::to_string_pretty(&lock)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::home_from;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| OsString::from(v))
+        }
+    }
+
+    #[test]
+    fn home_falls_back_to_windows_profile_vars() {
+        let unix = [("HOME", "/home/u"), ("USERPROFILE", r"C:\Users\u")];
+        assert_eq!(home_from(env(&unix)), Some(PathBuf::from("/home/u")));
+
+        let profile = [("HOME", ""), ("USERPROFILE", r"C:\Users\u")];
+        assert_eq!(home_from(env(&profile)), Some(PathBuf::from(r"C:\Users\u")));
+
+        let drive = [("HOMEDRIVE", "D:"), ("HOMEPATH", r"\Users\u")];
+        assert_eq!(home_from(env(&drive)), Some(PathBuf::from(r"D:\Users\u")));
+
+        assert_eq!(home_from(env(&[("HOMEDRIVE", "D:")])), None);
+    }
+}
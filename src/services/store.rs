@@ -0,0 +1,460 @@
+use crate::domain::models::{CheckItem, State};
+use crate::services::glob::glob_match;
+use crate::services::storage::{runtime_plugin_dir, store_base_dir};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+/// Marker file in directories pater materialized: runtime views and skill
+/// copies. Never part of a tree's hash.
+pub const MANAGED_MARKER: &str = ".pater-managed";
+
+pub fn store_entry_path(hash: &str) -> anyhow::Result<PathBuf> {
+    Ok(store_base_dir()?.join(hash))
+}
+
+const VCS_DIRS: [&str; 4] = [".git", ".hg", ".svn", ".bzr"];
+const IGNORE_FILE: &str = ".paterignore";
+
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[derive(Debug, PartialEq)]
+enum TreeEntry {
+    Dir(String),
+    File { rel: String, exec: bool },
+    Symlink { rel: String, target: PathBuf },
+}
+
+struct IgnorePattern {
+    glob: String,
+    dir_only: bool,
+    anchored: bool,
+}
+
+/// Gitignore-style subset read from `.paterignore` at the plugin root:
+/// `#` comments, trailing `/` for dirs only, leading `/` or an inner `/` to
+/// anchor at the root. Negation is not supported.
+struct IgnoreRules(Vec<IgnorePattern>);
+
+impl IgnoreRules {
+    fn load(root: &Path) -> Self {
+        let raw = std::fs::read_to_string(root.join(IGNORE_FILE)).unwrap_or_default();
+        let patterns = raw
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| {
+                let dir_only = l.ends_with('/');
+                let l = l.trim_end_matches('/');
+                let anchored = l.starts_with('/') || l.contains('/');
+                IgnorePattern {
+                    glob: l.trim_start_matches('/').to_string(),
+                    dir_only,
+                    anchored,
+                }
+            })
+            .collect();
+        Self(patterns)
+    }
+
+    fn is_ignored(&self, rel: &str, is_dir: bool) -> bool {
+        let base = rel.rsplit('/').next().unwrap_or(rel);
+        self.0.iter().any(|p| {
+            (!p.dir_only || is_dir)
+                && if p.anchored {
+                    glob_match(&p.glob, rel)
+                } else {
+                    glob_match(&p.glob, base)
+                }
+        })
+    }
+}
+
+/// Rejects absolute targets and relative ones that climb above the root.
+/// Targets may end at another link but not pass through one: `..` after a
+/// link is resolved from wherever that link points, so a chain of links that
+/// each stay in bounds lexically could still escape.
+fn check_symlink_target(root: &Path, rel: &str, target: &Path) -> anyhow::Result<()> {
+    let escapes = || {
+        anyhow::anyhow!(
+            "plugin symlink escapes plugin root: {} -> {}",
+            rel,
+            target.display()
+        )
+    };
+    if target.is_absolute() || target.has_root() {
+        return Err(escapes());
+    }
+    let mut resolved = Path::new(rel)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let mut components = target.components().peekable();
+    while let Some(component) = components.next() {
+        match component {
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return Err(escapes());
+                }
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                let is_link = std::fs::symlink_metadata(root.join(&resolved))
+                    .is_ok_and(|m| m.file_type().is_symlink());
+                if is_link && components.peek().is_some() {
+                    anyhow::bail!(
+                        "plugin symlink passes through another symlink: {} -> {}",
+                        rel,
+                        target.display()
+                    );
+                }
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return Err(escapes()),
+        }
+    }
+    Ok(())
+}
+
+fn sorted_entries(dir: &Path) -> anyhow::Result<Vec<std::fs::DirEntry>> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    Ok(entries)
+}
+
+fn collect_into(
+    root: &Path,
+    dir: &Path,
+    rules: &IgnoreRules,
+    out: &mut Vec<TreeEntry>,
+) -> anyhow::Result<()> {
+    for entry in sorted_entries(dir)? {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name == MANAGED_MARKER || VCS_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+        let path = entry.path();
+        let rel = path
+            .strip_prefix(root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        // Never follows links: `symlink_metadata` describes the entry itself.
+        let meta = std::fs::symlink_metadata(&path)?;
+        let ty = meta.file_type();
+        if rules.is_ignored(&rel, ty.is_dir()) {
+            continue;
+        }
+        if ty.is_symlink() {
+            let target = std::fs::read_link(&path)?;
+            check_symlink_target(root, &rel, &target)?;
+            out.push(TreeEntry::Symlink { rel, target });
+        } else if ty.is_dir() {
+            out.push(TreeEntry::Dir(rel));
+            collect_into(root, &path, rules, out)?;
+        } else if ty.is_file() {
+            out.push(TreeEntry::File {
+                rel,
+                exec: is_executable(&meta),
+            });
+        } else {
+            anyhow::bail!("unsupported special file in plugin: {}", rel);
+        }
+    }
+    Ok(())
+}
+
+/// Walks a plugin tree without following symlinks, skipping VCS metadata
+/// and `.paterignore` matches.
+fn collect_tree(root: &Path) -> anyhow::Result<Vec<TreeEntry>> {
+    let rules = IgnoreRules::load(root);
+    let mut out = Vec::new();
+    collect_into(root, root, &rules, &mut out)?;
+    Ok(out)
+}
+
+/// Stable content hash of a plugin tree: paths, exec bits, symlink targets
+/// and file bytes.
+pub fn tree_hash(root: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    for entry in collect_tree(root)? {
+        let line = match entry {
+            TreeEntry::Dir(rel) => format!("D {}\n", rel),
+            TreeEntry::File { rel, exec } => {
+                let content = hex::encode(Sha256::digest(std::fs::read(root.join(&rel))?));
+                let mode = if exec { "x" } else { "-" };
+                format!("F {} {} {}\n", rel, mode, content)
+            }
+            TreeEntry::Symlink { rel, target } => {
+                format!(
+                    "L {} {}\n",
+                    rel,
+                    target.to_string_lossy().replace('\\', "/")
+                )
+            }
+        };
+        hasher.update(line.as_bytes());
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(unix)]
+fn make_symlink(target: &Path, link: &Path) -> anyhow::Result<()> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn make_symlink(target: &Path, link: &Path) -> anyhow::Result<()> {
+    let resolved = link.parent().unwrap_or(Path::new(".")).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)?;
+    } else {
+        std::os::windows::fs::symlink_file(target, link)?;
+    }
+    Ok(())
+}
+
+/// Recreates `src`'s tree under `dst`, placing files with `place_file`.
+fn replicate_tree(
+    src: &Path,
+    dst: &Path,
+    place_file: fn(&Path, &Path) -> std::io::Result<()>,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in collect_tree(src)? {
+        match entry {
+            TreeEntry::Dir(rel) => std::fs::create_dir_all(dst.join(rel))?,
+            TreeEntry::File { rel, .. } => place_file(&src.join(&rel), &dst.join(&rel))?,
+            TreeEntry::Symlink { rel, target } => make_symlink(&target, &dst.join(rel))?,
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::copy(from, to).map(|_| ())
+}
+
+fn link_or_copy_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::hard_link(from, to).or_else(|_| copy_file(from, to))
+}
+
+/// Copies a tree without following symlinks, under the same rules as the
+/// store (in-root links kept, VCS metadata and ignored paths skipped).
+pub fn copy_tree(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    replicate_tree(src, dst, copy_file)
+}
+
+/// Copies `src` into the store unless an identical tree is already there.
+pub fn import_tree(src: &Path) -> anyhow::Result<(String, PathBuf)> {
+    let hash = tree_hash(src)?;
+    let entry = store_entry_path(&hash)?;
+    if entry.exists() {
+        return Ok((hash, entry));
+    }
+    let base = store_base_dir()?;
+    std::fs::create_dir_all(&base)?;
+    let staging = base.join(format!(".tmp-{}-{}", hash, std::process::id()));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    replicate_tree(src, &staging, copy_file)?;
+    if let Err(e) = std::fs::rename(&staging, &entry) {
+        let _ = std::fs::remove_dir_all(&staging);
+        if !entry.exists() {
+            return Err(e.into());
+        }
+    }
+    Ok((hash, entry))
+}
+
+/// Builds `dst` as a hardlink farm over a store entry (copying when the
+/// store sits on another filesystem). Symlinks are recreated, not linked.
+pub fn link_view(hash: &str, dst: &Path) -> anyhow::Result<()> {
+    let entry = store_entry_path(hash)?;
+    if dst.exists() {
+        std::fs::remove_dir_all(dst)?;
+    }
+    replicate_tree(&entry, dst, link_or_copy_file)?;
+    std::fs::write(
+        dst.join(MANAGED_MARKER),
+        format!("managed-by=pater\nstore={}\n", hash),
+    )?;
+    Ok(())
+}
+
+/// Store hash recorded in a runtime view's marker file, if any.
+pub fn view_store_hash(dir: &Path) -> Option<String> {
+    let raw = std::fs::read_to_string(dir.join(MANAGED_MARKER)).ok()?;
+    raw.lines()
+        .find_map(|l| l.strip_prefix("store="))
+        .map(|h| h.trim().to_string())
+}
+
+/// Recomputes the hash of a store entry; hardlinked views share inodes with
+/// the store, so this also catches in-place edits of runtime files.
+pub fn verify_entry(hash: &str) -> anyhow::Result<bool> {
+    let entry = store_entry_path(hash)?;
+    if !entry.exists() {
+        return Ok(false);
+    }
+    Ok(tree_hash(&entry)? == hash)
+}
+
+/// Integrity report for every installed plugin's store entry.
+pub fn verify_installed(state: &State) -> anyhow::Result<Vec<CheckItem>> {
+    let mut out = Vec::new();
+    for p in &state.installed {
+        let hash = p
+            .store_hash
+            .clone()
+            .or_else(|| view_store_hash(&runtime_plugin_dir(&p.marketplace, &p.name).ok()?));
+        let status = match hash {
+            None => "unmanaged",
+            Some(h) if !store_entry_path(&h)?.exists() => "missing_store_entry",
+            Some(h) if verify_entry(&h)? => "ok",
+            Some(_) => "corrupt",
+        };
+        out.push(CheckItem {
+            name: format!("{}@{}", p.name, p.marketplace),
+            status: status.to_string(),
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_tree, copy_tree, tree_hash, TreeEntry};
+
+    #[test]
+    fn tree_hash_ignores_marker_and_tracks_content() {
+        let a = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(a.path().join("skills")).unwrap();
+        std::fs::write(a.path().join("skills/SKILL.md"), "one").unwrap();
+        let first = tree_hash(a.path()).unwrap();
+
+        std::fs::write(a.path().join(".pater-managed"), "managed-by=pater\n").unwrap();
+        assert_eq!(tree_hash(a.path()).unwrap(), first);
+
+        std::fs::write(a.path().join("skills/SKILL.md"), "two").unwrap();
+        assert_ne!(tree_hash(a.path()).unwrap(), first);
+    }
+
+    #[test]
+    fn vcs_metadata_and_paterignore_matches_are_skipped() {
+        let a = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(a.path().join(".git/objects")).unwrap();
+        std::fs::write(a.path().join(".git/HEAD"), "ref").unwrap();
+        std::fs::create_dir_all(a.path().join("build")).unwrap();
+        std::fs::write(a.path().join("build/out.bin"), "bin").unwrap();
+        std::fs::write(a.path().join("debug.log"), "log").unwrap();
+        std::fs::write(a.path().join("README.md"), "keep").unwrap();
+        std::fs::write(
+            a.path().join(".paterignore"),
+            "# local junk\nbuild/\n*.log\n",
+        )
+        .unwrap();
+
+        let names: Vec<String> = collect_tree(a.path())
+            .unwrap()
+            .into_iter()
+            .map(|e| match e {
+                TreeEntry::Dir(rel) | TreeEntry::File { rel, .. } => rel,
+                TreeEntry::Symlink { rel, .. } => rel,
+            })
+            .collect();
+        assert_eq!(names, vec![".paterignore", "README.md"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_inside_root_are_preserved() {
+        let a = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(a.path().join("skills/review")).unwrap();
+        std::fs::write(a.path().join("skills/review/SKILL.md"), "x").unwrap();
+        std::os::unix::fs::symlink("review", a.path().join("skills/latest")).unwrap();
+
+        let entries = collect_tree(a.path()).unwrap();
+        assert!(entries.contains(&TreeEntry::Symlink {
+            rel: "skills/latest".to_string(),
+            target: "review".into(),
+        }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_tree_keeps_links_instead_of_following_them() {
+        let a = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(a.path().join("src/review")).unwrap();
+        std::fs::write(a.path().join("src/review/SKILL.md"), "x").unwrap();
+        std::os::unix::fs::symlink("review", a.path().join("src/latest")).unwrap();
+
+        let dst = a.path().join("copy");
+        copy_tree(&a.path().join("src"), &dst).unwrap();
+        assert_eq!(
+            std::fs::read_link(dst.join("latest")).unwrap(),
+            std::path::PathBuf::from("review")
+        );
+        assert_eq!(
+            tree_hash(&dst).unwrap(),
+            tree_hash(&a.path().join("src")).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escaping_symlinks_are_rejected() {
+        for target in ["/etc/passwd", "../../outside", "../x/../../y"] {
+            let a = tempfile::TempDir::new().unwrap();
+            std::fs::create_dir_all(a.path().join("skills")).unwrap();
+            std::os::unix::fs::symlink(target, a.path().join("skills/evil")).unwrap();
+            let err = collect_tree(a.path()).unwrap_err();
+            assert!(
+                err.to_string().contains("escapes plugin root"),
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chained_symlinks_cannot_escape() {
+        let a = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(a.path().join("a")).unwrap();
+        // Each link stays in bounds on its own; `b` resolves to the parent
+        // of the root because `a/up` already points at the root.
+        std::os::unix::fs::symlink("..", a.path().join("a/up")).unwrap();
+        std::os::unix::fs::symlink("a/up/..", a.path().join("b")).unwrap();
+        let err = collect_tree(a.path()).unwrap_err();
+        assert!(err.to_string().contains("passes through another symlink"));
+
+        std::fs::remove_file(a.path().join("b")).unwrap();
+        std::os::unix::fs::symlink("a/up", a.path().join("b")).unwrap();
+        assert!(collect_tree(a.path()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files_are_rejected() {
+        let a = tempfile::TempDir::new().unwrap();
+        let fifo = a.path().join("pipe");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let err = collect_tree(a.path()).unwrap_err();
+        assert!(err.to_string().contains("unsupported special file"));
+    }
+}
@@ -1,9 +1,9 @@
 use crate::rack;
+use crate::services::storage::home_dir;
 use std::path::PathBuf;
 
 fn trusted_pubkeys_path() -> anyhow::Result<PathBuf> {
-    let home = std::env::var("HOME")?;
-    Ok(PathBuf::from(home).join(".config/pater/trust/pubkeys.txt"))
+    Ok(home_dir()?.join(".config/pater/trust/pubkeys.txt"))
 }
 
 pub fn list_pubkeys() -> anyhow::Result<Vec<String>> {
@@ -0,0 +1,89 @@
+use crate::cli::ALL_ADAPTERS;
+use crate::domain::models::UninstallReport;
+use crate::rack;
+use crate::services::adapters::unsync_adapters;
+use crate::services::storage::{
+    adapter_backups_dir, config_base_dir, data_base_dir, lockfile_path, state_path,
+};
+use std::path::{Path, PathBuf};
+
+fn remove_if_exists(path: &Path, removed: &mut Vec<String>) -> anyhow::Result<()> {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if meta.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    removed.push(path.to_string_lossy().to_string());
+    Ok(())
+}
+
+/// State/lockfile plus their `*.v<N>.bak` migration backups.
+fn state_files() -> anyhow::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for path in [state_path()?, lockfile_path()?] {
+        let prefix = format!(
+            "{}.",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        if let Some(dir) = path.parent().filter(|d| d.exists()) {
+            for entry in std::fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&prefix) && name.ends_with(".bak") {
+                    out.push(entry.path());
+                }
+            }
+        }
+        out.push(path);
+    }
+    Ok(out)
+}
+
+/// Reverts every adapter, then deletes runtime/store/cache data and pater's
+/// state. Agent file backups (including those unsync just wrote) are kept
+/// and reported. User-authored config (policy, trust keys, adapter specs,
+/// audit log) is kept unless `purge` is set.
+pub fn self_uninstall(purge: bool) -> anyhow::Result<UninstallReport> {
+    let unsynced = unsync_adapters(ALL_ADAPTERS)?;
+
+    let mut removed = Vec::new();
+    let mut kept = Vec::new();
+    let data = data_base_dir()?;
+    let backups = adapter_backups_dir()?;
+    if data.exists() {
+        for entry in std::fs::read_dir(&data)?.filter_map(|e| e.ok()) {
+            if entry.path() != backups {
+                remove_if_exists(&entry.path(), &mut removed)?;
+            }
+        }
+    }
+    if backups.exists() {
+        kept.push(backups.to_string_lossy().to_string());
+    }
+    remove_if_exists(&rack::cache_root()?, &mut removed)?;
+
+    let config = config_base_dir()?;
+    if purge {
+        remove_if_exists(&config, &mut removed)?;
+    } else {
+        for path in state_files()? {
+            remove_if_exists(&path, &mut removed)?;
+        }
+        if config.exists() {
+            let mut config_kept = std::fs::read_dir(&config)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path().to_string_lossy().to_string())
+                .collect::<Vec<_>>();
+            config_kept.sort();
+            kept.extend(config_kept);
+        }
+    }
+
+    Ok(UninstallReport {
+        unsynced,
+        removed,
+        kept,
+    })
+}
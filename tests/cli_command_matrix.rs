@@ -26,6 +26,7 @@ fn every_cli_command_has_help_path() {
     run_help(&home, &["apply"]);
     run_help(&home, &["update"]);
     run_help(&home, &["remove"]);
+    run_help(&home, &["gc"]);
     run_help(&home, &["list"]);
     run_help(&home, &["capabilities"]);
     run_help(&home, &["hook"]);
@@ -36,6 +37,7 @@ fn every_cli_command_has_help_path() {
     run_help(&home, &["policy"]);
     run_help(&home, &["adapter"]);
     run_help(&home, &["runtime"]);
+    run_help(&home, &["self"]);
 
     // grouped subcommands
     run_help(&home, &["hook", "list"]);
@@ -44,13 +46,21 @@ fn every_cli_command_has_help_path() {
     run_help(&home, &["remote", "update"]);
 
     run_help(&home, &["policy", "eval"]);
+    run_help(&home, &["policy", "show"]);
+    run_help(&home, &["policy", "audit"]);
 
+    run_help(&home, &["adapter", "list"]);
     run_help(&home, &["adapter", "sync"]);
     run_help(&home, &["adapter", "smoke"]);
+    run_help(&home, &["adapter", "pin"]);
+    run_help(&home, &["adapter", "unpin"]);
+    run_help(&home, &["adapter", "unsync"]);
     run_help(&home, &["adapter", "doctor"]);
     run_help(&home, &["runtime", "path"]);
     run_help(&home, &["runtime", "status"]);
+    run_help(&home, &["runtime", "verify"]);
     run_help(&home, &["runtime", "sync"]);
+    run_help(&home, &["self", "uninstall"]);
 
     run_help(&home, &["trust"]);
     run_help(&home, &["trust", "init"]);
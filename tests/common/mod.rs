@@ -9,6 +9,10 @@ pub struct TestEnv {
     _tmp: TempDir,
     pub home: PathBuf,
     pub rack: PathBuf,
+    /// Prepended to `PATH`; holds a stub for the fixture's MCP command.
+    pub bin: PathBuf,
+    /// Stands in for `/etc/pater` (`PATER_SYSTEM_CONFIG_DIR`).
+    pub system_config: PathBuf,
     cargo_home: PathBuf,
     rustup_home: PathBuf,
 }
@@ -19,7 +23,13 @@ impl TestEnv {
         let home = tmp.path().join("home");
         fs::create_dir_all(&home).expect("create isolated home");
 
-        let rack = make_fixture_rack(tmp.path());
+        let rack = make_fixture_rack(tmp.path(), "rack", "fixture-rack");
+
+        let bin = tmp.path().join("bin");
+        fs::create_dir_all(&bin).expect("create stub bin dir");
+        fs::write(bin.join("mcp-github"), "#!/bin/sh\n").expect("write mcp stub");
+
+        let system_config = tmp.path().join("etc/pater");
 
         let orig_home = std::env::var("HOME").unwrap_or_default();
         let cargo_home = PathBuf::from(&orig_home).join(".cargo");
@@ -29,6 +39,8 @@ impl TestEnv {
             _tmp: tmp,
             home,
             rack,
+            bin,
+            system_config,
             cargo_home,
             rustup_home,
         }
@@ -36,7 +48,14 @@ impl TestEnv {
 
     pub fn cmd(&self) -> Command {
         let mut cmd = cargo_bin_cmd!("pater");
+        let path = std::env::join_paths(std::iter::once(self.bin.clone()).chain(
+            std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+        ))
+        .expect("join PATH");
         cmd.env("HOME", &self.home)
+            .env("PATH", path)
+            .env("PATER_SYSTEM_CONFIG_DIR", &self.system_config)
+            .env_remove("PATER_POLICY")
             .env("CARGO_HOME", &self.cargo_home)
             .env("RUSTUP_HOME", &self.rustup_home);
         cmd
@@ -55,6 +74,12 @@ impl TestEnv {
         serde_json::from_slice(&out).expect("valid json output")
     }
 
+    /// Second fixture marketplace with the same plugins under another name.
+    pub fn extra_rack(&self, dir: &str, market_name: &str) -> PathBuf {
+        let base = self.rack.parent().expect("rack parent");
+        make_fixture_rack(base, dir, market_name)
+    }
+
     pub fn run_json_market(&self, args: &[&str]) -> Value {
         let mut cmd = self.cmd();
         let out = cmd
@@ -71,8 +96,8 @@ impl TestEnv {
     }
 }
 
-fn make_fixture_rack(base: &Path) -> PathBuf {
-    let rack = base.join("rack");
+fn make_fixture_rack(base: &Path, dir: &str, market_name: &str) -> PathBuf {
+    let rack = base.join(dir);
     let commit = rack.join("plugins/commit-commands");
 
     fs::create_dir_all(rack.join(".pater")).expect("create .pater");
@@ -91,7 +116,12 @@ fn make_fixture_rack(base: &Path) -> PathBuf {
             "version": "1.0.0",
             "license": "MIT",
             "mcps": [
-                {"name": "github", "command": "mcp-github"}
+                {
+                    "name": "github",
+                    "command": "mcp-github",
+                    "args": ["--root", "${CLAUDE_PLUGIN_ROOT}"],
+                    "env": {"GITHUB_TOKEN_FILE": "~/.github-token"}
+                }
             ]
         })
         .to_string(),
@@ -104,7 +134,7 @@ fn make_fixture_rack(base: &Path) -> PathBuf {
     .expect("write skill file");
 
     let marketplace = serde_json::json!({
-        "name": "fixture-rack",
+        "name": market_name,
         "owner": {"name": "Fixture", "email": "fixture@example.com"},
         "plugins": [
             {
@@ -114,7 +144,10 @@ fn make_fixture_rack(base: &Path) -> PathBuf {
                 "version": "1.0.0",
                 "permissions": ["filesystem.read"],
                 "skills": ["commit-guidelines"],
-                "hooks": [{"agent": "codex", "event": "on-demand", "run": "echo ok"}],
+                "hooks": [
+                    {"agent": "codex", "event": "on-demand", "run": "echo ok"},
+                    {"agent": "claude", "event": "pre-tool:Bash", "run": "${CLAUDE_PLUGIN_ROOT}/bin/check"}
+                ],
                 "subagents": [{"name": "commit-helper", "purpose": "assist commit work"}]
             }
         ]
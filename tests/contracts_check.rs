@@ -109,6 +109,7 @@ fn contracts_check() {
             "typescript-lsp@fixture-rack",
             "--agent",
             "all",
+            "--explain",
         ],
     );
     assert_eq!(pol["ok"], true);
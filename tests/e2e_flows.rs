@@ -92,45 +92,1010 @@ fn apply_and_adapter_smoke_for_codex_target() {
     assert_eq!(smoke["data"][0]["checked_plugins"], 1);
 }
 
+#[test]
+fn adapter_list_and_unknown_target_error() {
+    let env = TestEnv::new();
+
+    let list = env.run_json(&["adapter", "list"]);
+    let names: Vec<&str> = list["data"]
+        .as_array()
+        .expect("adapter names")
+        .iter()
+        .filter_map(|v| v.as_str())
+        .collect();
+    assert_eq!(names, vec!["claude", "codex", "openclaw"]);
+
+    let mut cmd = env.cmd();
+    let out = cmd
+        .args(["--json", "adapter", "sync", "--target", "nope"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let err: Value = serde_json::from_slice(&out).expect("error json output");
+    let msg = err["error"]["message"].as_str().unwrap_or("");
+    assert!(msg.contains("unknown adapter: nope"));
+}
+
+#[test]
+fn adapter_unsync_removes_shim_bridge_and_wrapper() {
+    let env = TestEnv::new();
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+    assert!(env.home.join(".codex/pater.plugins.json").exists());
+    assert!(env.home.join(".local/bin/pater-codex").exists());
+
+    let unsync = env.run_json(&["adapter", "unsync", "--target", "codex"]);
+    assert_eq!(unsync["data"][0]["adapter"], "codex");
+    assert_eq!(unsync["data"][0]["config"], "restored");
+    assert!(!env.home.join(".codex/pater.plugins.json").exists());
+    assert!(!env.home.join(".local/bin/pater-codex").exists());
+    assert!(!env
+        .home
+        .join(".local/share/pater/runtime/bridges/codex.json")
+        .exists());
+    // pater created ~/.codex, so restoring leaves nothing behind.
+    assert!(!env.home.join(".codex").exists());
+
+    // Other adapters are untouched.
+    assert!(env.home.join(".claude/pater.plugins.json").exists());
+}
+
+#[test]
+fn unsync_restores_preexisting_agent_configs_byte_for_byte() {
+    let env = TestEnv::new();
+
+    let claude_cfg = env.home.join(".claude/settings.json");
+    let codex_cfg = env.home.join(".codex/config.toml");
+    fs::create_dir_all(claude_cfg.parent().expect("claude dir")).expect("create claude dir");
+    fs::create_dir_all(codex_cfg.parent().expect("codex dir")).expect("create codex dir");
+    let claude_before = "{\"theme\":   \"dark\"}\n";
+    let codex_before = "# my codex config\nmodel = \"o3\"\n";
+    fs::write(&claude_cfg, claude_before).expect("seed claude");
+    fs::write(&codex_cfg, codex_before).expect("seed codex");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+    assert_ne!(
+        fs::read_to_string(&claude_cfg).expect("claude"),
+        claude_before
+    );
+    // A second sync must not replace the snapshot with patched content.
+    env.run_json(&["adapter", "sync"]);
+
+    let unsync = env.run_json(&["adapter", "unsync"]);
+    assert_eq!(unsync["data"][0]["config"], "restored");
+    assert_eq!(
+        fs::read_to_string(&claude_cfg).expect("claude"),
+        claude_before
+    );
+    assert_eq!(fs::read_to_string(&codex_cfg).expect("codex"), codex_before);
+}
+
+#[test]
+fn unsync_keeps_user_edits_made_after_sync() {
+    let env = TestEnv::new();
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let claude_cfg = env.home.join(".claude/settings.json");
+    let mut v: Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    v["theme"] = Value::String("light".to_string());
+    fs::write(&claude_cfg, serde_json::to_string(&v).expect("ser")).expect("edit claude");
+
+    let unsync = env.run_json(&["adapter", "unsync", "--target", "claude"]);
+    assert_eq!(unsync["data"][0]["config"], "unpatched");
+    let after: Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    assert_eq!(after["theme"], "light");
+    assert!(after.get("pater").is_none());
+}
+
+#[test]
+fn unsync_keeps_user_edits_made_between_syncs() {
+    let env = TestEnv::new();
+
+    let codex_cfg = env.home.join(".codex/config.toml");
+    fs::create_dir_all(codex_cfg.parent().expect("codex dir")).expect("create codex dir");
+    fs::write(&codex_cfg, "model = \"o3\"\n").expect("seed codex");
+
+    let _install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    let patched = fs::read_to_string(&codex_cfg).expect("codex");
+    fs::write(&codex_cfg, format!("approval = \"never\"\n{}", patched)).expect("edit codex");
+    // The next sync rewrites the file, so its hash matches pater's write again.
+    env.run_json(&["adapter", "sync"]);
+
+    let unsync = env.run_json(&["adapter", "unsync", "--target", "codex"]);
+    assert_eq!(unsync["data"][0]["config"], "unpatched");
+    let after: toml::Table =
+        toml::from_str(&fs::read_to_string(&codex_cfg).expect("codex")).expect("toml");
+    assert_eq!(after["approval"].as_str(), Some("never"));
+    assert_eq!(after["model"].as_str(), Some("o3"));
+    assert!(after.get("pater").is_none());
+}
+
+#[test]
+fn codex_config_is_edited_in_place_preserving_comments() {
+    let env = TestEnv::new();
+
+    let codex_cfg = env.home.join(".codex/config.toml");
+    fs::create_dir_all(codex_cfg.parent().expect("codex dir")).expect("create codex dir");
+    fs::write(
+        &codex_cfg,
+        "# personal codex config\nmodel = \"o3\" # fast\n\n[pater]\nprofile = \"work\"\n\n# >>> pater managed start >>>\n# <<< pater managed end <<<\n\n[history]\npersistence = \"none\"\n",
+    )
+    .expect("seed codex");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let patched = fs::read_to_string(&codex_cfg).expect("codex");
+    assert!(patched.starts_with("# personal codex config\nmodel = \"o3\" # fast\n"));
+    assert!(patched.contains("[pater]\nprofile = \"work\"\nplugin_dirs = ["));
+    assert!(patched.contains("[history]\npersistence = \"none\"\n"));
+    assert!(!patched.contains("pater managed"));
+    assert_eq!(patched.matches("[pater]").count(), 1);
+    let parsed: toml::Table = toml::from_str(&patched).expect("patched config parses");
+    assert_eq!(
+        parsed["pater"]["plugin_dirs"]
+            .as_array()
+            .expect("plugin_dirs")
+            .len(),
+        1
+    );
+
+    let smoke = env.run_json(&["adapter", "smoke", "--target", "codex"]);
+    assert_eq!(smoke["data"][0]["status"], "ok");
+}
+
+fn smoke_check<'a>(report: &'a Value, name: &str) -> &'a Value {
+    report["checks"]
+        .as_array()
+        .expect("checks")
+        .iter()
+        .find(|c| c["name"] == name)
+        .unwrap_or_else(|| panic!("no {} check in {}", name, report))
+}
+
+#[test]
+fn adapter_smoke_reads_back_synced_config() {
+    let env = TestEnv::new();
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let smoke = env.run_json(&["adapter", "smoke"]);
+    for report in smoke["data"].as_array().expect("reports") {
+        assert_eq!(report["status"], "ok", "{}", report);
+    }
+    let claude = &smoke["data"][0];
+    for name in [
+        "shim",
+        "bridge",
+        "runtime_plugins",
+        "registry",
+        "claude_settings",
+        "wrapper",
+        "mcp:commit-commands__github",
+    ] {
+        assert_eq!(smoke_check(claude, name)["status"], "ok");
+    }
+    // OpenClaw reads its shim directly, so there is no separate config check.
+    let openclaw = smoke["data"][2]["checks"].as_array().expect("checks");
+    assert!(!openclaw.iter().any(|c| c["name"] == "openclaw_index"));
+
+    fs::remove_file(env.bin.join("mcp-github")).expect("remove mcp stub");
+    let smoke = env.run_json(&["adapter", "smoke", "--target", "claude"]);
+    assert_eq!(smoke["data"][0]["status"], "mcp_command_not_found");
+    let mcp = smoke_check(&smoke["data"][0], "mcp:commit-commands__github");
+    assert_eq!(mcp["detail"], "mcp-github");
+
+    let cfg = env.home.join(".codex/config.toml");
+    let codex = fs::read_to_string(&cfg).expect("read codex config");
+    let runtime_dir = serde_json::from_str::<Value>(
+        &fs::read_to_string(env.home.join(".codex/pater.plugins.json")).expect("read shim"),
+    )
+    .expect("shim json")["plugin_dirs"][0]
+        .as_str()
+        .expect("plugin dir")
+        .to_string();
+    fs::write(&cfg, codex.replace(&runtime_dir, "/gone")).expect("edit codex config");
+    let smoke = env.run_json(&["adapter", "smoke", "--target", "codex"]);
+    let report = &smoke["data"][0];
+    assert_eq!(report["status"], "stale_plugin_dirs");
+    let detail = smoke_check(report, "codex_config")["detail"]
+        .as_str()
+        .expect("detail")
+        .to_string();
+    assert!(detail.contains("unexpected /gone"), "{}", detail);
+
+    fs::remove_dir_all(&runtime_dir).expect("remove runtime dir");
+    let smoke = env.run_json(&["adapter", "smoke", "--target", "claude"]);
+    let report = &smoke["data"][0];
+    assert_eq!(report["status"], "missing_runtime_plugins");
+    assert_eq!(report["missing_plugins"][0], "commit-commands");
+    assert_eq!(smoke_check(report, "wrapper")["status"], "broken_wrapper");
+    assert_eq!(smoke_check(report, "wrapper")["detail"], runtime_dir);
+}
+
+#[test]
+fn plugin_mcp_servers_are_registered_natively_and_removed_with_the_plugin() {
+    let env = TestEnv::new();
+
+    let claude_cfg = env.home.join(".claude/settings.json");
+    fs::create_dir_all(claude_cfg.parent().expect("claude dir")).expect("create claude dir");
+    fs::write(
+        &claude_cfg,
+        r#"{"mcpServers": {"mine": {"command": "my-mcp"}}}"#,
+    )
+    .expect("seed claude settings");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let claude: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    let server = &claude["mcpServers"]["commit-commands__github"];
+    assert_eq!(server["command"], "mcp-github");
+    assert_eq!(server["args"][0], "--root");
+    assert!(server["args"][1]
+        .as_str()
+        .expect("root arg")
+        .ends_with("runtime/plugins/fixture-rack/commit-commands"));
+    assert_eq!(server["env"]["GITHUB_TOKEN_FILE"], "~/.github-token");
+    assert_eq!(claude["mcpServers"]["mine"]["command"], "my-mcp");
+
+    let codex_cfg = env.home.join(".codex/config.toml");
+    let codex: toml::Table =
+        toml::from_str(&fs::read_to_string(&codex_cfg).expect("codex")).expect("toml");
+    assert_eq!(
+        codex["mcp_servers"]["commit-commands__github"]["command"].as_str(),
+        Some("mcp-github")
+    );
+    // Resync leaves registered servers where they are instead of moving
+    // the Codex tables to the end of the file.
+    let plan = env.run_json(&["adapter", "sync", "--target", "codex", "--dry-run"]);
+    assert_eq!(plan["data"], serde_json::json!([]), "resync is a no-op");
+
+    let remove = env.run_json(&["remove", "commit-commands"]);
+    assert_eq!(remove["ok"], true);
+
+    let claude: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    assert!(claude["mcpServers"]
+        .get("commit-commands__github")
+        .is_none());
+    assert_eq!(claude["mcpServers"]["mine"]["command"], "my-mcp");
+    let codex = fs::read_to_string(&codex_cfg).expect("codex");
+    assert!(!codex.contains("mcp_servers"));
+}
+
+#[test]
+fn plugin_hooks_are_written_as_claude_hooks_and_unsupported_ones_reported() {
+    let env = TestEnv::new();
+
+    let claude_cfg = env.home.join(".claude/settings.json");
+    fs::create_dir_all(claude_cfg.parent().expect("claude dir")).expect("create claude dir");
+    let own_hook = serde_json::json!({"hooks": [{"type": "command", "command": "my-lint"}]});
+    fs::write(
+        &claude_cfg,
+        serde_json::json!({"hooks": {"PreToolUse": [own_hook]}}).to_string(),
+    )
+    .expect("seed claude settings");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let claude: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    let groups = claude["hooks"]["PreToolUse"]
+        .as_array()
+        .expect("PreToolUse");
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0], own_hook);
+    assert_eq!(groups[1]["matcher"], "Bash");
+    assert_eq!(groups[1]["hooks"][0]["type"], "command");
+    assert!(groups[1]["hooks"][0]["command"]
+        .as_str()
+        .expect("command")
+        .ends_with("runtime/plugins/fixture-rack/commit-commands/bin/check"));
+
+    let sync = env.run_json(&["adapter", "sync"]);
+    assert_eq!(sync["data"]["status"], "synced");
+    let skipped = sync["data"]["skipped_hooks"].as_array().expect("skipped");
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0]["adapter"], "codex");
+    assert_eq!(skipped[0]["plugin"], "commit-commands");
+    assert_eq!(skipped[0]["event"], "on-demand");
+
+    let claude: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    assert_eq!(
+        claude["hooks"]["PreToolUse"]
+            .as_array()
+            .expect("hooks")
+            .len(),
+        2,
+        "resync must not duplicate hooks"
+    );
+
+    let remove = env.run_json(&["remove", "commit-commands"]);
+    assert_eq!(remove["ok"], true);
+    let claude: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&claude_cfg).expect("claude")).expect("json");
+    assert_eq!(claude["hooks"]["PreToolUse"], serde_json::json!([own_hook]));
+}
+
+#[test]
+fn subagents_become_claude_agent_files_and_codex_prompts() {
+    let env = TestEnv::new();
+
+    let create = env.run_json(&[
+        "author",
+        "subagent",
+        "create",
+        "commit-commands",
+        "reviewer",
+        "--rack-dir",
+        env.rack.to_str().expect("rack path"),
+        "--purpose",
+        "Review staged changes",
+        "--prompt",
+        "You review staged diffs before commit.",
+        "--tool",
+        "Read",
+        "--tool",
+        "Bash",
+        "--model",
+        "haiku",
+    ]);
+    assert_eq!(create["ok"], true);
+
+    let agents_dir = env.home.join(".claude/agents");
+    fs::create_dir_all(&agents_dir).expect("create agents dir");
+    fs::write(agents_dir.join("mine.md"), "---\nname: mine\n---\n").expect("seed own agent");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let reviewer = fs::read_to_string(agents_dir.join("commit-commands-reviewer.md"))
+        .expect("claude agent file");
+    assert!(reviewer.starts_with("---\nname: commit-commands-reviewer\n"));
+    assert!(reviewer.contains("description: Review staged changes\n"));
+    assert!(reviewer.contains("tools: Read, Bash\nmodel: haiku\n"));
+    assert!(reviewer.ends_with("---\n\nYou review staged diffs before commit.\n"));
+    let helper = fs::read_to_string(agents_dir.join("commit-commands-commit-helper.md"))
+        .expect("purpose-only agent file");
+    assert!(helper.ends_with("\n\nassist commit work\n"));
+    assert!(env
+        .home
+        .join(".codex/prompts/commit-commands-reviewer.md")
+        .exists());
+
+    let remove = env.run_json(&["remove", "commit-commands"]);
+    assert_eq!(remove["ok"], true);
+    assert!(!agents_dir.join("commit-commands-reviewer.md").exists());
+    assert!(!agents_dir.join("commit-commands-commit-helper.md").exists());
+    assert!(agents_dir.join("mine.md").exists());
+    assert!(!env
+        .home
+        .join(".codex/prompts/commit-commands-reviewer.md")
+        .exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn skills_are_linked_into_native_skill_dirs_and_cleaned_up() {
+    let env = TestEnv::new();
+
+    let skills_dir = env.home.join(".claude/skills");
+    fs::create_dir_all(skills_dir.join("mine")).expect("seed own skill");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let link = skills_dir.join("commit-commands-commit-guidelines");
+    let target = fs::read_link(&link).expect("skill is a symlink");
+    assert!(
+        target.ends_with("runtime/plugins/fixture-rack/commit-commands/skills/commit-guidelines")
+    );
+    assert_eq!(
+        fs::read_to_string(link.join("SKILL.md")).expect("skill readable"),
+        "# Commit Guidelines\n"
+    );
+    assert!(env
+        .home
+        .join(".openclaw/workspace/skills/commit-commands-commit-guidelines")
+        .exists());
+
+    let remove = env.run_json(&["remove", "commit-commands"]);
+    assert_eq!(remove["ok"], true);
+    assert!(fs::symlink_metadata(&link).is_err());
+    assert!(skills_dir.join("mine").exists());
+}
+
+fn bridge_plugins(env: &TestEnv, adapter: &str) -> serde_json::Value {
+    let path = env
+        .home
+        .join(".local/share/pater/runtime/bridges")
+        .join(format!("{}.json", adapter));
+    let bridge: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(path).expect("bridge")).expect("json");
+    bridge["plugins"].clone()
+}
+
+#[test]
+fn supported_agents_limit_sync_and_policy_eval() {
+    let env = TestEnv::new();
+
+    let market_path = env.rack.join(".pater/marketplace.json");
+    let mut market: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&market_path).expect("market")).expect("json");
+    market["plugins"][0]["agents"] = serde_json::json!(["claude"]);
+    fs::write(&market_path, market.to_string()).expect("write market");
+
+    let eval = env.run_json_market(&["policy", "eval", "commit-commands", "--agent", "codex"]);
+    assert_eq!(eval["data"]["allowed"], false);
+    assert!(eval["data"]["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("does not support agent codex"));
+    let eval = env.run_json_market(&["policy", "eval", "commit-commands", "--agent", "claude"]);
+    assert_eq!(eval["data"]["allowed"], true);
+    assert_eq!(
+        eval["data"]["supported_agents"],
+        serde_json::json!(["claude"])
+    );
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+    assert_eq!(
+        bridge_plugins(&env, "claude"),
+        serde_json::json!(["commit-commands"])
+    );
+    assert_eq!(bridge_plugins(&env, "codex"), serde_json::json!([]));
+    assert!(!env
+        .home
+        .join(".codex/skills/commit-commands-commit-guidelines")
+        .exists());
+
+    let mut cmd = env.cmd();
+    let out = cmd
+        .args(["--json", "adapter", "pin", "commit-commands", "codex"])
+        .output()
+        .expect("run pin");
+    assert!(!out.status.success());
+    let err: serde_json::Value = serde_json::from_slice(&out.stdout).expect("json error");
+    assert!(err["error"]["message"]
+        .as_str()
+        .expect("message")
+        .contains("does not support agent codex"));
+}
+
+#[test]
+fn plugins_can_be_pinned_to_a_subset_of_adapters() {
+    let env = TestEnv::new();
+
+    let install = env.run_json_market(&[
+        "install",
+        "commit-commands@fixture-rack",
+        "--adapter",
+        "codex",
+    ]);
+    assert_eq!(install["data"]["adapters"], serde_json::json!(["codex"]));
+    assert_eq!(
+        bridge_plugins(&env, "codex"),
+        serde_json::json!(["commit-commands"])
+    );
+    assert_eq!(bridge_plugins(&env, "claude"), serde_json::json!([]));
+    let claude: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(env.home.join(".claude/settings.json")).expect("claude"),
+    )
+    .expect("json");
+    assert!(claude.get("mcpServers").is_none());
+
+    let unpin = env.run_json(&["adapter", "unpin", "commit-commands"]);
+    assert_eq!(unpin["ok"], true);
+    assert_eq!(
+        bridge_plugins(&env, "claude"),
+        serde_json::json!(["commit-commands"])
+    );
+
+    let apply = env.run_json_market(&[
+        "apply",
+        "commit-commands@fixture-rack",
+        "--target-adapter",
+        "claude",
+    ]);
+    assert_eq!(
+        apply["data"]["installed"]["adapters"],
+        serde_json::json!(["claude"])
+    );
+    assert_eq!(bridge_plugins(&env, "codex"), serde_json::json!([]));
+    assert_eq!(
+        bridge_plugins(&env, "claude"),
+        serde_json::json!(["commit-commands"])
+    );
+}
+
+#[test]
+fn unparseable_agent_config_is_refused_not_replaced() {
+    let env = TestEnv::new();
+
+    let claude_cfg = env.home.join(".claude/settings.json");
+    fs::create_dir_all(claude_cfg.parent().expect("claude dir")).expect("create claude dir");
+    let broken = "{ \"theme\": \"dark\", // trailing comment\n";
+    fs::write(&claude_cfg, broken).expect("seed broken claude config");
+
+    let mut cmd = env.cmd();
+    let out = cmd
+        .args(["--json", "--marketplace"])
+        .arg(&env.rack)
+        .args(["install", "commit-commands@fixture-rack"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let err: Value = serde_json::from_slice(&out).expect("error json output");
+    let msg = err["error"]["message"].as_str().unwrap_or("");
+    assert!(msg.contains("refusing to modify unparseable config"));
+    assert_eq!(fs::read_to_string(&claude_cfg).expect("claude"), broken);
+}
+
+#[test]
+fn adapter_sync_backs_up_prior_configs_and_dry_run_only_diffs() {
+    let env = TestEnv::new();
+
+    let claude_cfg = env.home.join(".claude/settings.json");
+    fs::create_dir_all(claude_cfg.parent().expect("claude dir")).expect("create claude dir");
+    fs::write(&claude_cfg, "{\"theme\": \"dark\"}").expect("seed claude");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let backups = env.home.join(".local/share/pater/backups");
+    let runs: Vec<_> = fs::read_dir(&backups)
+        .expect("backups dir")
+        .filter_map(|e| e.ok())
+        .collect();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(
+        fs::read_to_string(runs[0].path().join(".claude/settings.json")).expect("backup"),
+        "{\"theme\": \"dark\"}"
+    );
+
+    // Drop pater's key so the next sync has something to change.
+    fs::write(&claude_cfg, "{\"theme\": \"light\"}").expect("edit claude");
+    let plan = env.run_json(&["adapter", "sync", "--target", "claude", "--dry-run"]);
+    let changes = plan["data"].as_array().expect("changes");
+    let claude = changes
+        .iter()
+        .find(|c| c["path"] == claude_cfg.to_string_lossy().as_ref())
+        .expect("claude settings in plan");
+    assert_eq!(claude["action"], "modify");
+    let diff = claude["diff"].as_str().expect("diff");
+    assert!(diff.starts_with("--- a/"));
+    assert!(diff.contains("+  \"pater\": {"));
+    assert_eq!(
+        fs::read_to_string(&claude_cfg).expect("claude"),
+        "{\"theme\": \"light\"}"
+    );
+    assert_eq!(fs::read_dir(&backups).expect("backups dir").count(), 1);
+
+    let mut cmd = env.cmd();
+    let text = cmd
+        .args(["adapter", "sync", "--target", "claude", "--dry-run"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(String::from_utf8_lossy(&text).contains("@@"));
+}
+
+#[test]
+fn self_uninstall_reverts_adapters_and_removes_pater_data() {
+    let env = TestEnv::new();
+
+    let codex_cfg = env.home.join(".codex/config.toml");
+    fs::create_dir_all(codex_cfg.parent().expect("codex dir")).expect("create codex dir");
+    fs::write(&codex_cfg, "model = \"o3\"\n").expect("seed codex");
+    let policy = env.home.join(".config/pater/policy.toml");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+    fs::write(&policy, "[general]\n").expect("write policy");
+
+    let report = env.run_json(&["self", "uninstall"]);
+    assert_eq!(report["ok"], true);
+    assert_eq!(
+        fs::read_to_string(&codex_cfg).expect("codex"),
+        "model = \"o3\"\n"
+    );
+    assert!(!env.home.join(".claude").exists());
+    assert!(!env.home.join(".local/bin/pater-codex").exists());
+    let data = env.home.join(".local/share/pater");
+    assert!(!data.join("runtime").exists());
+    assert!(!data.join("adapter-originals").exists());
+    // Backups unsync just wrote survive and are reported.
+    let backups = data.join("backups");
+    assert!(backups.exists());
+    let kept = report["data"]["kept"].as_array().expect("kept");
+    assert!(kept.iter().any(|k| k.as_str() == backups.to_str()));
+    assert!(!env.home.join(".config/pater/state.json").exists());
+    assert!(policy.exists());
+
+    env.run_json(&["self", "uninstall", "--purge"]);
+    assert!(!env.home.join(".config/pater").exists());
+}
+
+#[test]
+fn declarative_adapter_from_config_dir_is_synced_like_builtins() {
+    let env = TestEnv::new();
+
+    let adapters_dir = env.home.join(".config/pater/adapters");
+    fs::create_dir_all(&adapters_dir).expect("create adapters dir");
+    fs::write(
+        adapters_dir.join("harness.toml"),
+        r#"
+name = "harness"
+config_path = "~/.harness/settings.yaml"
+format = "yaml"
+key = "extensions.pater"
+wrapper_command = "harness --profile work {plugin_args} --"
+plugin_dir_flag = "--extension-dir={dir}"
+"#,
+    )
+    .expect("write adapter spec");
+    let cfg = env.home.join(".harness/settings.yaml");
+    fs::create_dir_all(cfg.parent().expect("cfg parent")).expect("create harness dir");
+    fs::write(&cfg, "theme: dark\n").expect("seed harness config");
+
+    let list = env.run_json(&["adapter", "list"]);
+    assert_eq!(list["data"][3], "harness");
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+
+    let patched = fs::read_to_string(&cfg).expect("read harness config");
+    assert!(patched.contains("theme: dark"));
+    assert!(patched.contains("plugin_dirs:"));
+    assert!(patched.contains("fixture-rack/commit-commands"));
+
+    let wrapper =
+        fs::read_to_string(env.home.join(".local/bin/pater-harness")).expect("read wrapper");
+    assert!(wrapper.contains("exec harness --profile work --extension-dir='"));
+    assert!(wrapper.contains("' -- \"$@\""));
+
+    let smoke = env.run_json(&["adapter", "smoke", "--target", "harness"]);
+    assert_eq!(smoke["data"][0]["status"], "ok");
+
+    let doctor = env.run_json(&["adapter", "doctor"]);
+    let configs = doctor["data"]["configs"].as_array().expect("configs");
+    assert!(configs
+        .iter()
+        .any(|c| c["name"] == "harness_config" && c["status"] == "ok"));
+}
+
+#[test]
+fn declarative_json_adapter_owns_only_plugin_dirs_under_its_key() {
+    let env = TestEnv::new();
+
+    let adapters_dir = env.home.join(".config/pater/adapters");
+    fs::create_dir_all(&adapters_dir).expect("create adapters dir");
+    fs::write(
+        adapters_dir.join("bench.toml"),
+        "name = \"bench\"\nconfig_path = \"~/.bench/config.json\"\nformat = \"json\"\nkey = \"ext.pater\"\n",
+    )
+    .expect("write adapter spec");
+    let cfg = env.home.join(".bench/config.json");
+    fs::create_dir_all(cfg.parent().expect("cfg parent")).expect("create bench dir");
+    fs::write(&cfg, r#"{"ext": {"pater": {"profile": "work"}}}"#).expect("seed bench config");
+
+    let _install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    let patched: Value =
+        serde_json::from_str(&fs::read_to_string(&cfg).expect("read config")).expect("json");
+    assert_eq!(patched["ext"]["pater"]["profile"], "work");
+    assert_eq!(
+        patched["ext"]["pater"]["plugin_dirs"]
+            .as_array()
+            .expect("plugin_dirs")
+            .len(),
+        1
+    );
+
+    // Edit the file after sync so unsync removes entries in place.
+    let mut edited = patched.clone();
+    edited["theme"] = Value::from("dark");
+    fs::write(&cfg, serde_json::to_string(&edited).expect("render")).expect("edit config");
+    let _unsync = env.run_json(&["adapter", "unsync", "--target", "bench"]);
+    let unsynced: Value =
+        serde_json::from_str(&fs::read_to_string(&cfg).expect("read config")).expect("json");
+    assert_eq!(unsynced["theme"], "dark");
+    assert_eq!(
+        unsynced["ext"]["pater"],
+        serde_json::json!({"profile": "work"})
+    );
+}
+
 #[test]
 fn runtime_first_registry_and_bridge_flow() {
     let env = TestEnv::new();
 
     let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
-    assert_eq!(install["ok"], true);
+    assert_eq!(install["ok"], true);
+
+    let status = env.run_json(&["runtime", "status"]);
+    assert_eq!(status["ok"], true);
+    assert_eq!(status["data"]["registry_exists"], true);
+    assert_eq!(status["data"]["plugins_count"], 1);
+    assert_eq!(status["data"]["skills_count"], 1);
+    assert_eq!(status["data"]["hooks_count"], 2);
+    assert_eq!(status["data"]["subagents_count"], 1);
+    assert_eq!(status["data"]["mcps_count"], 1);
+
+    let runtime_plugin = env
+        .home
+        .join(".local/share/pater/runtime/plugins/fixture-rack/commit-commands");
+    assert!(runtime_plugin.exists());
+
+    // Runtime-first means adapter native plugin trees are not canonical and should not be copied.
+    assert!(!env.home.join(".codex/plugins/commit-commands").exists());
+    assert!(!env.home.join(".claude/plugins/commit-commands").exists());
+}
+
+#[test]
+fn policy_denies_install_for_blocked_permission() {
+    let env = TestEnv::new();
+
+    let policy_path = env.home.join(".config/pater/policy.toml");
+    fs::create_dir_all(policy_path.parent().expect("policy parent")).expect("create policy dir");
+    fs::write(
+        policy_path,
+        r#"[general]
+blocked_permissions = ["filesystem.read"]
+"#,
+    )
+    .expect("write policy file");
+
+    let mut cmd = env.cmd();
+    let out = cmd
+        .arg("--json")
+        .arg("--marketplace")
+        .arg(env.rack.to_str().expect("rack path utf8"))
+        .args(["install", "commit-commands@fixture-rack"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+
+    let err: Value = serde_json::from_slice(&out).expect("error json output");
+    assert_eq!(err["ok"], false);
+    assert_eq!(err["error"]["code"], "POLICY_DENY");
+    let msg = err["error"]["message"].as_str().unwrap_or("");
+    assert!(msg.contains("policy blocked permission in plugin"));
+}
+
+#[test]
+fn update_reports_up_to_date_when_no_change() {
+    let env = TestEnv::new();
+
+    let _install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+
+    let update = env.run_json(&["update", "commit-commands"]);
+    assert_eq!(update["ok"], true);
+
+    let reports = update["data"].as_array().expect("update reports");
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["name"], "commit-commands");
+    assert_eq!(reports[0]["status"], "up_to_date");
+}
+
+#[test]
+fn legacy_state_is_migrated_and_backed_up() {
+    let env = TestEnv::new();
+
+    let state_path = env.home.join(".config/pater/state.json");
+    fs::create_dir_all(state_path.parent().expect("state parent")).expect("create state dir");
+    fs::write(
+        &state_path,
+        r#"{"marketplaces":[],"installed":[{"name":"legacy","marketplace":"m","source":"./legacy","version":null}]}"#,
+    )
+    .expect("write legacy state");
+
+    let list = env.run_json(&["list"]);
+    assert_eq!(list["ok"], true);
+    assert_eq!(list["data"][0]["name"], "legacy");
+    assert_eq!(list["data"][0]["scope"], "user");
+
+    let migrated: Value =
+        serde_json::from_str(&fs::read_to_string(&state_path).expect("read state"))
+            .expect("state json");
+    assert_eq!(migrated["schema_version"], 1);
+    assert!(env.home.join(".config/pater/state.json.v0.bak").exists());
+}
+
+#[test]
+fn newer_state_schema_is_refused() {
+    let env = TestEnv::new();
+
+    let state_path = env.home.join(".config/pater/state.json");
+    fs::create_dir_all(state_path.parent().expect("state parent")).expect("create state dir");
+    fs::write(
+        &state_path,
+        r#"{"schema_version":99,"marketplaces":[],"installed":[]}"#,
+    )
+    .expect("write future state");
+
+    let mut cmd = env.cmd();
+    let out = cmd
+        .args(["--json", "list"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let err: Value = serde_json::from_slice(&out).expect("error json output");
+    let msg = err["error"]["message"].as_str().unwrap_or("");
+    assert!(msg.contains("newer than supported"));
+}
+
+#[test]
+fn gc_dry_run_reports_and_gc_prunes_unreferenced_dirs() {
+    let env = TestEnv::new();
+
+    let _install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+
+    let runtime_plugins = env.home.join(".local/share/pater/runtime/plugins");
+    let stray_plugin = runtime_plugins.join("stale-plugin");
+    fs::create_dir_all(&stray_plugin).expect("create stray plugin");
+    fs::write(stray_plugin.join("README.md"), "stale").expect("write stray file");
+    let stray_repo = env.home.join(".cache/pater/repos/deadbeef");
+    fs::create_dir_all(&stray_repo).expect("create stray repo");
+
+    let dry = env.run_json(&["gc", "--dry-run"]);
+    assert_eq!(dry["ok"], true);
+    assert_eq!(dry["data"]["dry_run"], true);
+    assert_eq!(dry["data"]["items"].as_array().expect("gc items").len(), 2);
+    assert_eq!(dry["data"]["reclaimed_bytes"], 5);
+    assert!(stray_plugin.exists());
+    assert!(stray_repo.exists());
+
+    let gc = env.run_json(&["gc"]);
+    assert_eq!(gc["data"]["dry_run"], false);
+    assert!(!stray_plugin.exists());
+    assert!(!stray_repo.exists());
+    assert!(runtime_plugins
+        .join("fixture-rack/commit-commands")
+        .exists());
+
+    // Remove prunes only the plugin's own dirs; other garbage waits for gc.
+    fs::create_dir_all(&stray_repo).expect("recreate stray repo");
+    let _remove = env.run_json(&["remove", "commit-commands"]);
+    assert!(!runtime_plugins.join("fixture-rack").exists());
+    assert!(stray_repo.exists());
+}
+
+#[test]
+fn same_plugin_from_two_marketplaces_is_namespaced() {
+    let env = TestEnv::new();
+    let other = env.extra_rack("other", "other-rack");
+
+    let _first = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    let _remote = env.run_json(&["remote", "add", other.to_str().expect("rack path utf8")]);
+    let _second = env.run_json_market(&["install", "commit-commands@other-rack"]);
+
+    let runtime_plugins = env.home.join(".local/share/pater/runtime/plugins");
+    assert!(runtime_plugins
+        .join("fixture-rack/commit-commands")
+        .exists());
+    assert!(runtime_plugins.join("other-rack/commit-commands").exists());
+
+    let mut cmd = env.cmd();
+    let out = cmd
+        .args(["--json", "remove", "commit-commands"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let err: Value = serde_json::from_slice(&out).expect("error json output");
+    let msg = err["error"]["message"].as_str().unwrap_or("");
+    assert!(msg.contains("ambiguous plugin name"));
+
+    let mut cmd = env.cmd();
+    cmd.args([
+        "--json",
+        "--marketplace",
+        env.rack.to_str().expect("rack path utf8"),
+    ])
+    .args(["show", "commit-commands"])
+    .assert()
+    .failure();
+
+    let remove = env.run_json(&["remove", "commit-commands@other-rack"]);
+    assert_eq!(remove["data"], 1);
+    assert!(!runtime_plugins.join("other-rack").exists());
+    assert!(runtime_plugins
+        .join("fixture-rack/commit-commands")
+        .exists());
+}
+
+#[test]
+fn runtime_views_are_hardlinked_to_store_and_verified() {
+    let env = TestEnv::new();
+
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    let hash = install["data"]["store_hash"]
+        .as_str()
+        .expect("store hash")
+        .to_string();
 
-    let status = env.run_json(&["runtime", "status"]);
-    assert_eq!(status["ok"], true);
-    assert_eq!(status["data"]["registry_exists"], true);
-    assert_eq!(status["data"]["plugins_count"], 1);
-    assert_eq!(status["data"]["skills_count"], 1);
-    assert_eq!(status["data"]["hooks_count"], 1);
-    assert_eq!(status["data"]["subagents_count"], 1);
-    assert_eq!(status["data"]["mcps_count"], 1);
+    let store_entry = env.home.join(".local/share/pater/store").join(&hash);
+    let runtime_skill = env.home.join(
+        ".local/share/pater/runtime/plugins/fixture-rack/commit-commands/skills/commit-guidelines/SKILL.md",
+    );
+    assert!(store_entry.exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let store_meta = fs::metadata(store_entry.join("skills/commit-guidelines/SKILL.md"))
+            .expect("store file");
+        let view_meta = fs::metadata(&runtime_skill).expect("runtime file");
+        assert_eq!(store_meta.ino(), view_meta.ino());
+    }
 
-    let runtime_plugin = env
-        .home
-        .join(".local/share/pater/runtime/plugins/commit-commands");
-    assert!(runtime_plugin.exists());
+    let verify = env.run_json(&["runtime", "verify"]);
+    assert_eq!(verify["ok"], true);
+    assert_eq!(verify["data"][0]["status"], "ok");
 
-    // Runtime-first means adapter native plugin trees are not canonical and should not be copied.
-    assert!(!env.home.join(".codex/plugins/commit-commands").exists());
-    assert!(!env.home.join(".claude/plugins/commit-commands").exists());
+    fs::write(&runtime_skill, "tampered").expect("tamper runtime file");
+    let mut cmd = env.cmd();
+    let out = cmd
+        .args(["--json", "runtime", "verify"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&out).expect("verify json output");
+    assert_eq!(report["data"][0]["status"], "corrupt");
+
+    let _remove = env.run_json(&["remove", "commit-commands"]);
+    assert!(!store_entry.exists());
 }
 
 #[test]
-fn policy_denies_install_for_blocked_permission() {
+fn materialization_skips_vcs_and_ignored_files() {
     let env = TestEnv::new();
+    let plugin = env.rack.join("plugins/commit-commands");
+    fs::create_dir_all(plugin.join(".git")).expect("create .git");
+    fs::write(plugin.join(".git/config"), "[core]").expect("write git config");
+    fs::write(plugin.join("notes.tmp"), "scratch").expect("write ignored file");
+    fs::write(plugin.join(".paterignore"), "*.tmp\n").expect("write .paterignore");
 
-    let policy_path = env.home.join(".config/pater/policy.toml");
-    fs::create_dir_all(policy_path.parent().expect("policy parent")).expect("create policy dir");
-    fs::write(
-        policy_path,
-        r#"[general]
-blocked_permissions = ["filesystem.read"]
-"#,
-    )
-    .expect("write policy file");
+    let _install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+
+    let runtime = env
+        .home
+        .join(".local/share/pater/runtime/plugins/fixture-rack/commit-commands");
+    assert!(runtime.join("LICENSE").exists());
+    assert!(!runtime.join(".git").exists());
+    assert!(!runtime.join("notes.tmp").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn install_rejects_symlink_escaping_plugin_root() {
+    let env = TestEnv::new();
+    let plugin = env.rack.join("plugins/commit-commands");
+    std::os::unix::fs::symlink("../../../../etc/passwd", plugin.join("skills/leak"))
+        .expect("plant symlink");
 
     let mut cmd = env.cmd();
     let out = cmd
@@ -143,25 +1108,632 @@ blocked_permissions = ["filesystem.read"]
         .get_output()
         .stdout
         .clone();
-
     let err: Value = serde_json::from_slice(&out).expect("error json output");
-    assert_eq!(err["ok"], false);
-    assert_eq!(err["error"]["code"], "POLICY_DENY");
     let msg = err["error"]["message"].as_str().unwrap_or("");
-    assert!(msg.contains("policy blocked permission in plugin"));
+    assert!(msg.contains("escapes plugin root"));
+    assert!(!env
+        .home
+        .join(".local/share/pater/runtime/plugins/fixture-rack/commit-commands")
+        .exists());
 }
 
 #[test]
-fn update_reports_up_to_date_when_no_change() {
+fn home_dir_falls_back_to_userprofile_without_home() {
     let env = TestEnv::new();
+    let out = env
+        .cmd()
+        .env_remove("HOME")
+        .env_remove("HOMEDRIVE")
+        .env("USERPROFILE", &env.home)
+        .args(["--json", "runtime", "path"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let path: Value = serde_json::from_slice(&out).expect("valid json output");
+    let base = path["data"]["base"].as_str().expect("base");
+    assert!(base.starts_with(env.home.to_str().expect("home utf8")));
 
-    let _install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    let err = env
+        .cmd()
+        .env_remove("HOME")
+        .env_remove("USERPROFILE")
+        .env_remove("HOMEDRIVE")
+        .env_remove("HOMEPATH")
+        .args(["--json", "runtime", "path"])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let err: Value = serde_json::from_slice(&err).expect("valid json error");
+    assert!(err["error"]["message"]
+        .as_str()
+        .expect("message")
+        .contains("cannot determine home directory"));
+}
+
+#[test]
+fn policy_layers_merge_tighten_only_with_origins() {
+    let env = TestEnv::new();
+    fs::create_dir_all(&env.system_config).expect("create system config dir");
+    fs::write(
+        env.system_config.join("policy.toml"),
+        "[general]\nallow_external_reference_installs = false\ndenied_plugins = [\"legacy\"]\n",
+    )
+    .expect("write system policy");
+    let user = env.home.join(".config/pater");
+    fs::create_dir_all(&user).expect("create user config dir");
+    fs::write(
+        user.join("policy.toml"),
+        "[general]\nallow_external_reference_installs = true\nblock_unknown_licenses = true\n",
+    )
+    .expect("write user policy");
+    let repo = env.home.join("work/repo");
+    fs::create_dir_all(repo.join(".git")).expect("create repo");
+    fs::create_dir_all(repo.join(".pater")).expect("create repo .pater");
+    fs::write(
+        repo.join(".pater/policy.toml"),
+        "[general]\ndenied_plugins = [\"commit-commands\"]\n",
+    )
+    .expect("write project policy");
+    let nested = repo.join("src/deep");
+    fs::create_dir_all(&nested).expect("create nested dir");
+
+    let run_in = |dir: &std::path::Path, args: &[&str]| -> Value {
+        let out = env
+            .cmd()
+            .current_dir(dir)
+            .arg("--json")
+            .arg("--marketplace")
+            .arg(env.rack.to_str().expect("rack path utf8"))
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        serde_json::from_slice(&out).expect("valid json output")
+    };
+
+    let show = run_in(&nested, &["policy", "show", "--effective"]);
+    let layers = show["data"]["layers"].as_array().expect("layers");
+    let names = layers.iter().map(|l| l["name"].clone()).collect::<Vec<_>>();
+    assert_eq!(names, ["system", "user", "project"]);
+    assert!(layers.iter().all(|l| l["loaded"] == true));
+    assert_eq!(
+        layers[2]["settings"]["general"]["denied_plugins"][0],
+        "commit-commands"
+    );
+
+    let field = |name: &str| -> Value {
+        show["data"]["effective"]
+            .as_array()
+            .expect("effective")
+            .iter()
+            .find(|f| f["field"] == name)
+            .cloned()
+            .expect("field")
+    };
+    assert_eq!(field("allow_external_reference_installs")["value"], false);
+    assert_eq!(
+        field("allow_external_reference_installs")["origin"],
+        serde_json::json!(["system"])
+    );
+    assert_eq!(
+        field("denied_plugins")["value"],
+        serde_json::json!(["legacy", "commit-commands"])
+    );
+    assert_eq!(
+        field("denied_plugins")["origin"],
+        serde_json::json!(["system", "project"])
+    );
+    assert_eq!(
+        field("block_unknown_licenses")["origin"],
+        serde_json::json!(["user"])
+    );
+    assert_eq!(field("allowed_sources")["value"], Value::Null);
+    assert_eq!(
+        field("require_signed_marketplace")["origin"],
+        serde_json::json!([])
+    );
+
+    let eval = run_in(&nested, &["policy", "eval", "commit-commands@fixture-rack"]);
+    assert_eq!(eval["data"]["allowed"], false);
+    assert!(eval["data"]["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("policy denied plugin"));
+
+    // Outside the repo the project layer does not apply.
+    let eval = run_in(
+        &env.home,
+        &["policy", "eval", "commit-commands@fixture-rack"],
+    );
+    assert_eq!(eval["data"]["allowed"], true);
+
+    let override_file = env.home.join("ci-policy.toml");
+    fs::write(
+        &override_file,
+        "[general]\nrequire_signed_marketplace = false\n",
+    )
+    .expect("write env policy");
+    let out = env
+        .cmd()
+        .current_dir(&env.home)
+        .env("PATER_POLICY", &override_file)
+        .args(["--json", "policy", "show"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let show: Value = serde_json::from_slice(&out).expect("valid json output");
+    assert_eq!(show["data"]["layers"][3]["name"], "env");
+    assert!(show["data"].get("effective").is_none());
+}
+
+#[test]
+fn policy_lists_accept_globs_and_marketplace_qualifiers() {
+    let env = TestEnv::new();
+    let cfg = env.home.join(".config/pater");
+    fs::create_dir_all(&cfg).expect("create config dir");
+    let eval = |policy: &str| -> Value {
+        fs::write(cfg.join("policy.toml"), policy).expect("write policy");
+        env.run_json_market(&["policy", "eval", "commit-commands@fixture-rack"])["data"].clone()
+    };
+
+    let other_market = eval("[general]\ndenied_plugins = [\"commit-*@other-rack\"]\n");
+    assert_eq!(other_market["allowed"], true);
+
+    let this_market = eval("[general]\ndenied_plugins = [\"commit-*@fixture-*\"]\n");
+    assert_eq!(this_market["allowed"], false);
+    assert!(this_market["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("policy denied plugin"));
+
+    let permission = eval("[general]\nblocked_permissions = [\"filesystem.*\"]\n");
+    assert_eq!(permission["allowed"], false);
+    assert!(permission["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("blocked permission"));
+}
+
+#[test]
+fn permission_allowlist_mode_names_tripped_permissions() {
+    let env = TestEnv::new();
+    let cfg = env.home.join(".config/pater");
+    fs::create_dir_all(&cfg).expect("create config dir");
+    fs::create_dir_all(&env.system_config).expect("create system config dir");
+    fs::write(
+        env.system_config.join("policy.toml"),
+        "[general]\npermission_mode = \"allowlist\"\nallowed_permissions = [\"network.*\", \"filesystem.*\"]\n",
+    )
+    .expect("write system policy");
+    let eval = |policy: &str| -> Value {
+        fs::write(cfg.join("policy.toml"), policy).expect("write policy");
+        env.run_json_market(&["policy", "eval", "commit-commands"])["data"].clone()
+    };
+
+    let denied = eval("[general]\nallowed_permissions = [\"network.*\"]\n");
+    assert_eq!(denied["allowed"], false);
+    assert_eq!(
+        denied["blocked_permissions"],
+        serde_json::json!(["filesystem.read"])
+    );
+    assert!(denied["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("(filesystem.read)"));
+
+    // A user layer cannot switch a system allowlist back to denylist.
+    let allowed = eval(
+        "[general]\npermission_mode = \"denylist\"\nallowed_permissions = [\"filesystem.*\"]\n",
+    );
+    assert_eq!(allowed["allowed"], true);
+    assert_eq!(allowed["blocked_permissions"], serde_json::json!([]));
+
+    let shown = env.run_json(&["policy", "show", "--effective"]);
+    let mode = shown["data"]["effective"]
+        .as_array()
+        .expect("effective")
+        .iter()
+        .find(|f| f["field"] == "permission_mode")
+        .cloned()
+        .expect("permission_mode");
+    assert_eq!(mode["value"], "allowlist");
+    assert_eq!(mode["origin"], serde_json::json!(["system"]));
+}
+
+fn set_fixture_permissions(env: &TestEnv, permissions: &[&str]) {
+    let path = env.rack.join(".pater/marketplace.json");
+    let mut market: Value =
+        serde_json::from_str(&fs::read_to_string(&path).expect("read marketplace"))
+            .expect("marketplace json");
+    market["plugins"][0]["permissions"] = serde_json::json!(permissions);
+    fs::write(&path, market.to_string()).expect("write marketplace");
+}
+
+#[test]
+fn blocked_update_keeps_supported_agents() {
+    let env = TestEnv::new();
+    env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+
+    let path = env.rack.join(".pater/marketplace.json");
+    let mut market: Value =
+        serde_json::from_str(&fs::read_to_string(&path).expect("read marketplace"))
+            .expect("marketplace json");
+    market["plugins"][0]["agents"] = serde_json::json!(["claude"]);
+    market["plugins"][0]["permissions"] = serde_json::json!(["exec:git"]);
+    fs::write(&path, market.to_string()).expect("write marketplace");
 
     let update = env.run_json(&["update", "commit-commands"]);
-    assert_eq!(update["ok"], true);
+    assert_eq!(update["data"][0]["status"], "blocked_permission_increase");
+    let list = env.run_json(&["list"]);
+    // Still "any agent": the blocked update must not apply the new list.
+    assert!(list["data"][0].get("agents").is_none());
+    env.run_json(&["adapter", "sync"]);
+    assert_eq!(
+        bridge_plugins(&env, "codex"),
+        serde_json::json!(["commit-commands"])
+    );
+}
 
-    let reports = update["data"].as_array().expect("update reports");
-    assert_eq!(reports.len(), 1);
-    assert_eq!(reports[0]["name"], "commit-commands");
-    assert_eq!(reports[0]["status"], "up_to_date");
+#[test]
+fn update_flags_broadened_typed_permissions() {
+    let env = TestEnv::new();
+    set_fixture_permissions(&env, &["fs:read:src/**", "net:api.github.com"]);
+    env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+
+    set_fixture_permissions(&env, &["fs:read:**", "net:api.github.com", "exec:git"]);
+    let update = env.run_json(&["update", "commit-commands"]);
+    let report = &update["data"][0];
+    assert_eq!(report["status"], "blocked_permission_increase");
+    assert_eq!(
+        report["broadened_permissions"],
+        serde_json::json!(["fs:read:**"])
+    );
+    assert_eq!(report["added_permissions"], serde_json::json!(["exec:git"]));
+
+    // Narrowing a scope is not an increase.
+    set_fixture_permissions(&env, &["fs:read:src/lib/**"]);
+    let update = env.run_json(&["update", "commit-commands"]);
+    assert_eq!(update["data"][0]["status"], "updated");
+
+    let cfg = env.home.join(".config/pater");
+    fs::write(
+        cfg.join("policy.toml"),
+        "[general]\nblocked_permissions = [\"fs:read\"]\n",
+    )
+    .expect("write policy");
+    let eval = env.run_json_market(&["policy", "eval", "commit-commands"]);
+    assert_eq!(eval["data"]["allowed"], false);
+    assert_eq!(
+        eval["data"]["blocked_permissions"],
+        serde_json::json!(["fs:read:src/lib/**"])
+    );
+
+    set_fixture_permissions(&env, &["fs:delete:src"]);
+    env.cmd()
+        .arg("--marketplace")
+        .arg(&env.rack)
+        .arg("validate")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "invalid permission fs:delete:src",
+        ));
+}
+
+#[test]
+fn malformed_permissions_fail_closed() {
+    let env = TestEnv::new();
+    let policy = env.home.join(".config/pater/policy.toml");
+    fs::create_dir_all(policy.parent().expect("policy dir")).expect("create policy dir");
+    fs::write(
+        &policy,
+        "[general]\nblocked_permissions = [\"exec\", \"net\"]\n",
+    )
+    .expect("write policy");
+    set_fixture_permissions(&env, &["exec", "net"]);
+
+    // The marketplace still loads; only the plugin itself is refused.
+    let search = env.run_json_market(&["search", "commit"]);
+    assert!(search["data"]
+        .as_array()
+        .expect("search results")
+        .iter()
+        .any(|r| r["name"] == "commit-commands"));
+    let eval = env.run_json_market(&["policy", "eval", "commit-commands"]);
+    assert_eq!(eval["data"]["allowed"], false);
+    assert!(eval["data"]["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("invalid permission in plugin: commit-commands (exec, net)"));
+    assert_eq!(
+        eval["data"]["blocked_permissions"],
+        serde_json::json!(["exec", "net"])
+    );
+    env.cmd()
+        .args(["--marketplace"])
+        .arg(&env.rack)
+        .args(["install", "commit-commands@fixture-rack"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("invalid permission"));
+
+    fs::write(&policy, "[general]\nblocked_permissions = [\"net:\"]\n").expect("write policy");
+    env.cmd()
+        .args(["--marketplace"])
+        .arg(&env.rack)
+        .args(["policy", "eval", "commit-commands"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "invalid permission net: (expected",
+        ));
+}
+
+#[test]
+fn policy_eval_explain_traces_every_rule_against_candidate_file() {
+    let env = TestEnv::new();
+    let candidate = env.home.join("candidate.toml");
+    fs::write(
+        &candidate,
+        "[general]\ndenied_plugins = [\"commit-*\"]\nblocked_permissions = [\"filesystem.*\"]\n",
+    )
+    .expect("write candidate policy");
+
+    // The configured policy allows the plugin; the candidate denies it twice.
+    let current = env.run_json_market(&["policy", "eval", "commit-commands", "--explain"]);
+    assert_eq!(current["data"]["allowed"], true);
+
+    let eval = env.run_json_market(&[
+        "policy",
+        "eval",
+        "commit-commands",
+        "--explain",
+        "--policy",
+        candidate.to_str().expect("utf8 path"),
+    ])["data"]
+        .clone();
+    assert_eq!(eval["allowed"], false);
+    assert!(eval["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("policy denied plugin"));
+    let outcomes = eval["trace"]
+        .as_array()
+        .expect("trace")
+        .iter()
+        .map(|t| {
+            format!(
+                "{}={}",
+                t["rule"].as_str().unwrap_or(""),
+                t["outcome"].as_str().unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        [
+            "agents=skip",
+            "denied_plugins=deny",
+            "allowed_sources=skip",
+            "signed_marketplace=skip",
+            "permissions=deny",
+            "external_reference=skip",
+            "unknown_license=skip",
+        ]
+    );
+
+    let plain = env.run_json_market(&["policy", "eval", "commit-commands"]);
+    assert!(plain["data"].get("trace").is_none());
+}
+
+#[test]
+fn policy_audit_flags_and_quarantines_installed_violators() {
+    let env = TestEnv::new();
+    env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    let bridge = env
+        .home
+        .join(".local/share/pater/runtime/bridges/claude.json");
+    let bridge_dirs = || -> usize {
+        let raw = fs::read_to_string(&bridge).expect("read bridge");
+        let v: Value = serde_json::from_str(&raw).expect("bridge json");
+        v["plugin_dirs"].as_array().expect("plugin_dirs").len()
+    };
+    assert_eq!(bridge_dirs(), 1);
+
+    let audit = |args: &[&str], ok: bool| -> Value {
+        let assert = env.cmd().arg("--json").args(args).assert();
+        let assert = if ok {
+            assert.success()
+        } else {
+            assert.failure()
+        };
+        serde_json::from_slice(&assert.get_output().stdout).expect("audit json")
+    };
+    assert_eq!(audit(&["policy", "audit"], true)["data"]["violations"], 0);
+
+    // Tightening the policy afterwards is caught by audit.
+    fs::write(
+        env.home.join(".config/pater/policy.toml"),
+        "[general]\nblocked_permissions = [\"filesystem.*\"]\n",
+    )
+    .expect("write policy");
+    let report = audit(&["policy", "audit"], false);
+    assert_eq!(report["ok"], false);
+    assert_eq!(report["data"]["violations"], 1);
+    assert_eq!(report["data"]["plugins"][0]["quarantined"], false);
+    assert_eq!(bridge_dirs(), 1);
+
+    let report = audit(&["policy", "audit", "--quarantine"], false);
+    assert_eq!(report["data"]["plugins"][0]["quarantined"], true);
+    assert_eq!(bridge_dirs(), 0);
+    let list = env.run_json(&["list"]);
+    assert!(list["data"][0]["quarantined"]
+        .as_str()
+        .expect("quarantine reason")
+        .contains("blocked permission"));
+    let runtime = list["data"][0]["local_path"].as_str().expect("local_path");
+    assert!(std::path::Path::new(runtime).exists());
+
+    // Once the policy allows it again, a quarantine audit releases it.
+    fs::remove_file(env.home.join(".config/pater/policy.toml")).expect("remove policy");
+    env.cmd()
+        .args(["policy", "audit"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "\tquarantined\tallowed again; rerun with --quarantine to release",
+        ));
+    assert_eq!(bridge_dirs(), 0);
+    let report = audit(&["policy", "audit", "--quarantine"], true);
+    assert_eq!(report["data"]["plugins"][0]["quarantined"], false);
+    assert_eq!(bridge_dirs(), 1);
+
+    // A marketplace that no longer loads under the policy (here: it has no
+    // signature) is a violation, not a pass.
+    fs::write(
+        env.home.join(".config/pater/policy.toml"),
+        "[general]\nrequire_signed_marketplace = true\n",
+    )
+    .expect("write policy");
+    let report = audit(&["policy", "audit", "--quarantine"], false);
+    assert_eq!(report["data"]["violations"], 1);
+    assert_eq!(report["data"]["plugins"][0]["quarantined"], true);
+    assert!(report["data"]["plugins"][0]["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("cannot load marketplace"));
+}
+
+#[test]
+fn agent_policy_sections_filter_bridges_per_agent() {
+    let env = TestEnv::new();
+    let cfg = env.home.join(".config/pater");
+    fs::create_dir_all(&cfg).expect("create config dir");
+    fs::write(
+        cfg.join("policy.toml"),
+        "[agent.codex]\nblocked_permissions = [\"filesystem.*\"]\n",
+    )
+    .expect("write policy");
+
+    // General policy allows the install; only codex's bridge leaves it out.
+    let install = env.run_json_market(&["install", "commit-commands@fixture-rack"]);
+    assert_eq!(install["ok"], true);
+    let bridge_dirs = |adapter: &str| -> usize {
+        let path = env.home.join(format!(
+            ".local/share/pater/runtime/bridges/{}.json",
+            adapter
+        ));
+        let v: Value =
+            serde_json::from_str(&fs::read_to_string(path).expect("read bridge")).expect("json");
+        v["plugin_dirs"].as_array().expect("plugin_dirs").len()
+    };
+    assert_eq!(bridge_dirs("claude"), 1);
+    assert_eq!(bridge_dirs("codex"), 0);
+
+    let sync = env.run_json(&["adapter", "sync"]);
+    let skipped = sync["data"]["skipped_plugins"]
+        .as_array()
+        .expect("skipped_plugins");
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0]["adapter"], "codex");
+    assert!(skipped[0]["plugin"]
+        .as_str()
+        .expect("plugin")
+        .starts_with("commit-commands"));
+    assert!(skipped[0]["reason"]
+        .as_str()
+        .expect("reason")
+        .contains("filesystem.read"));
+
+    let codex = env.run_json_market(&["policy", "eval", "commit-commands", "--agent", "codex"]);
+    assert_eq!(codex["data"]["allowed"], false);
+    assert_eq!(
+        codex["data"]["blocked_permissions"],
+        serde_json::json!(["filesystem.read"])
+    );
+    let claude = env.run_json_market(&["policy", "eval", "commit-commands", "--agent", "claude"]);
+    assert_eq!(claude["data"]["allowed"], true);
+
+    let shown = env.run_json(&["policy", "show", "--effective"]);
+    let field = shown["data"]["effective"]
+        .as_array()
+        .expect("effective")
+        .iter()
+        .find(|f| f["field"] == "agent.codex.blocked_permissions")
+        .cloned()
+        .expect("agent field");
+    assert_eq!(field["origin"], serde_json::json!(["user:agent.codex"]));
+}
+
+#[test]
+fn expired_policy_exceptions_stop_applying_and_are_flagged_in_check() {
+    let env = TestEnv::new();
+    let path = env.rack.join(".pater/marketplace.json");
+    let mut market: Value =
+        serde_json::from_str(&fs::read_to_string(&path).expect("read marketplace"))
+            .expect("marketplace json");
+    market["plugins"][0]["distribution"] = serde_json::json!("external-reference-only");
+    fs::write(&path, market.to_string()).expect("write marketplace");
+
+    let cfg = env.home.join(".config/pater");
+    fs::create_dir_all(&cfg).expect("create config dir");
+    let with_exception = |expires: &str| {
+        fs::write(
+            cfg.join("policy.toml"),
+            format!(
+                r#"[general]
+allow_external_reference_plugins = [
+  {{ plugin = "commit-commands", reason = "vendor review SEC-12", approved_by = "security", expires = {} }},
+]
+"#,
+                expires
+            ),
+        )
+        .expect("write policy");
+    };
+
+    with_exception("2999-12-31");
+    let eval = env.run_json_market(&["policy", "eval", "commit-commands"]);
+    assert_eq!(eval["data"]["allowed"], true);
+    let check = env.run_json(&["check"]);
+    assert_eq!(
+        check["data"]["expired_policy_exceptions"],
+        serde_json::json!([])
+    );
+
+    with_exception("2000-01-31");
+    let eval = env.run_json_market(&["policy", "eval", "commit-commands"]);
+    assert_eq!(eval["data"]["allowed"], false);
+    let check = env.run_json(&["check"]);
+    assert_eq!(check["data"]["overall"], "needs_attention");
+    let expired = &check["data"]["expired_policy_exceptions"][0];
+    assert_eq!(expired["plugin"], "commit-commands");
+    assert_eq!(expired["layer"], "user");
+    assert_eq!(expired["field"], "allow_external_reference_plugins");
+    assert_eq!(expired["expires"], "2000-01-31");
+    assert_eq!(expired["approved_by"], "security");
+
+    with_exception("\"next week\"");
+    let out = env
+        .cmd()
+        .arg("--json")
+        .arg("list")
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let err: Value = serde_json::from_slice(&out).expect("error json");
+    assert!(err["error"]["message"]
+        .as_str()
+        .expect("message")
+        .contains("invalid expires date"));
 }